@@ -25,21 +25,21 @@ pub fn search(c: &mut Criterion) {
     #[rustfmt::skip]
     let requests = [
         // 10
-        ("No merge - small", Query { q: Some(S("Hello")), limit: 10 }),
+        ("No merge - small", Query { q: Some(S("Hello")), limit: 10, ..Query::default() }),
         // 100
-        ("No merge - medium", Query { q: Some(S("tour")), limit: 10 }),
+        ("No merge - medium", Query { q: Some(S("tour")), limit: 10, ..Query::default() }),
         // 1000
-        ("No merge - large", Query { q: Some(S("documentary")), limit: 10 }),
+        ("No merge - large", Query { q: Some(S("documentary")), limit: 10, ..Query::default() }),
         // 10_000
-        ("No merge - extra_large", Query { q: Some(S("and")), limit: 10 }),
+        ("No merge - extra_large", Query { q: Some(S("and")), limit: 10, ..Query::default() }),
         // 10
-        ("Merge - small", Query { q: Some(S("Hello lol")), limit: 10 }),
+        ("Merge - small", Query { q: Some(S("Hello lol")), limit: 10, ..Query::default() }),
         // 100
-        ("Merge - medium", Query { q: Some(S("color red")), limit: 10 }),
+        ("Merge - medium", Query { q: Some(S("color red")), limit: 10, ..Query::default() }),
         // 1000
-        ("Merge - large", Query { q: Some(S("Hello lol")), limit: 10 }),
+        ("Merge - large", Query { q: Some(S("Hello lol")), limit: 10, ..Query::default() }),
         // 10_000
-        ("Merge - extra_large", Query { q: Some(S("bob and his dog")), limit: 10 },
+        ("Merge - extra_large", Query { q: Some(S("bob and his dog")), limit: 10, ..Query::default() },
         ),
     ];
 
@@ -1,21 +1,64 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, Read};
 use std::time::Instant;
-use std::{borrow::Cow, sync::Arc};
+use std::{borrow::Cow, collections::HashSet, fmt, sync::Arc};
 
-use axum::{extract, response, routing::get, Router};
-use serde::{Deserialize, Serialize};
+use axum::{
+    async_trait,
+    body::{Body, Bytes, HttpBody},
+    extract::{self, FromRequest, RequestParts},
+    http::{self, header, HeaderMap, Method, StatusCode},
+    response::{self, IntoResponse},
+    routing::{get, post},
+    Router,
+};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::{json, Value};
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock, RwLockReadGuard};
 
-use crate::{DocId, Index as RawIndex};
+use crate::{
+    filter::Filter,
+    formats::{
+        extend_columns, read_documents, write_csv, write_csv_header, write_json, write_ndjson,
+        Format,
+    },
+    search::histograms,
+    suggest::Suggester,
+    vector::{parse_vector, VECTORS_FIELD},
+    DocId, Hit, Index as RawIndex, Rule, Rules, SearchError, Settings,
+};
 
 type Index<I> = Arc<RwLock<I>>;
 
+/// The uid of the index of the routes outside of `/indexes/:uid`.
+const DEFAULT_INDEX: &str = "default";
+
 pub async fn run<I: RawIndex + 'static>(index: I) {
-    let index = Arc::new(RwLock::new(index));
+    let indexes = Arc::new(Indexes::new(index));
     // our router
     let app = Router::new()
         .route("/", get(root))
+        .route("/indexes", get(list_indexes::<I>))
+        .route("/multi-search", post(multi_search::<I>))
+        .merge(index_routes::<I>())
+        .nest("/indexes/:uid", index_routes::<I>())
+        .layer(extract::Extension(indexes));
+
+    log::info!("Server started on `http://localhost:3000/`");
+
+    // run it with hyper on localhost:3000
+    axum::Server::bind(&"0.0.0.0:3000".parse().unwrap())
+        .serve(app.into_make_service())
+        .await
+        .unwrap();
+}
+
+/// The routes of an index, served for the default index and under `/indexes/:uid`.
+fn index_routes<I: RawIndex + 'static>() -> Router {
+    Router::new()
         .route("/documents/:docid", get(get_document::<I>))
+        .route("/documents/:docid/similar", get(similar_documents::<I>))
         .route(
             "/documents",
             get(get_documents::<I>)
@@ -23,41 +66,298 @@ pub async fn run<I: RawIndex + 'static>(index: I) {
                 .delete(delete_documents::<I>),
         )
         .route("/search", get(search::<I>))
-        .layer(extract::Extension(index));
+        .route("/suggest", get(suggest::<I>))
+        .route(
+            "/settings",
+            get(get_settings::<I>).post(update_settings::<I>),
+        )
+        .route("/rules", get(get_rules::<I>))
+        .route(
+            "/rules/:id",
+            get(get_rule::<I>)
+                .put(put_rule::<I>)
+                .delete(delete_rule::<I>),
+        )
+}
 
-    log::info!("Server started on `http://localhost:3000/`");
+/// An index of the server and the words suggested from it.
+struct Handle<I> {
+    index: Index<I>,
+    suggester: Arc<Suggester>,
+}
 
-    // run it with hyper on localhost:3000
-    axum::Server::bind(&"0.0.0.0:3000".parse().unwrap())
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+impl<I> Clone for Handle<I> {
+    fn clone(&self) -> Self {
+        Handle {
+            index: self.index.clone(),
+            suggester: self.suggester.clone(),
+        }
+    }
+}
+
+impl<I: RawIndex> Handle<I> {
+    fn new(mut index: I) -> Self {
+        index.migrate();
+        Handle {
+            suggester: Arc::new(Suggester::new(&index)),
+            index: Arc::new(RwLock::new(index)),
+        }
+    }
+}
+
+/// The indexes of the server by uid. The indexes stored by a previous run are opened at
+/// startup, the other ones are created by their first write.
+struct Indexes<I> {
+    handles: std::sync::RwLock<BTreeMap<String, Handle<I>>>,
+}
+
+impl<I: RawIndex> Indexes<I> {
+    fn new(default: I) -> Self {
+        let mut handles = BTreeMap::new();
+        for uid in I::uids() {
+            log::info!("Opening the index `{uid}`");
+            handles.insert(uid.clone(), Handle::new(I::open(&uid)));
+        }
+        handles.insert(DEFAULT_INDEX.to_string(), Handle::new(default));
+        Indexes {
+            handles: std::sync::RwLock::new(handles),
+        }
+    }
+
+    fn get(&self, uid: &str) -> Option<Handle<I>> {
+        self.handles.read().unwrap().get(uid).cloned()
+    }
+
+    /// The index `uid`, created if it doesn't exist.
+    fn get_or_create(&self, uid: &str) -> Result<Handle<I>, String> {
+        if let Some(handle) = self.get(uid) {
+            return Ok(handle);
+        }
+        // the uid is the name of the directory of the index
+        if uid.is_empty()
+            || !uid
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Invalid index uid `{uid}`, only alphanumeric characters, `-` and `_` are allowed."
+            ));
+        }
+        let mut handles = self.handles.write().unwrap();
+        let handle = handles
+            .entry(uid.to_string())
+            .or_insert_with(|| Handle::new(I::open(uid)));
+        Ok(handle.clone())
+    }
+}
+
+/// The index of a request: the one named by `/indexes/:uid` or the default one. The reads of an
+/// index that doesn't exist are refused, the writes create it.
+#[async_trait]
+impl<I: RawIndex + 'static, B: Send> FromRequest<B> for Handle<I> {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        let extract::Extension(indexes) = extract::Extension::<Arc<Indexes<I>>>::from_request(req)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        let extract::Path(params) = extract::Path::<HashMap<String, String>>::from_request(req)
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        let uid = params.get("uid").map_or(DEFAULT_INDEX, String::as_str);
+
+        match *req.method() {
+            Method::POST | Method::PUT => indexes
+                .get_or_create(uid)
+                .map_err(|e| (StatusCode::BAD_REQUEST, e)),
+            _ => indexes
+                .get(uid)
+                .ok_or((StatusCode::NOT_FOUND, format!("Index `{uid}` not found."))),
+        }
+    }
 }
 
 // which calls one of these handlers
 async fn root() -> &'static str {
-    "Call `/documents` or `/search`"
+    "Call `/documents`, `/search`, `/multi-search`, `/suggest`, `/settings`, `/rules` or `/indexes`"
+}
+
+async fn list_indexes<I: RawIndex>(
+    extract::Extension(indexes): extract::Extension<Arc<Indexes<I>>>,
+) -> response::Json<Vec<String>> {
+    response::Json(indexes.handles.read().unwrap().keys().cloned().collect())
+}
+
+/// The parameters of the routes of a document, the `uid` of the index is read by `Handle`.
+#[derive(Deserialize)]
+struct DocumentPath {
+    docid: DocId,
+}
+
+/// The parameters of the routes of a curation rule.
+#[derive(Deserialize)]
+struct RulePath {
+    id: String,
 }
 
 async fn get_document<I: RawIndex>(
-    extract::Extension(index): extract::Extension<Index<I>>,
-    extract::Path(docid): extract::Path<DocId>,
+    Handle { index, .. }: Handle<I>,
+    extract::Path(DocumentPath { docid }): extract::Path<DocumentPath>,
 ) -> response::Json<Option<Document>> {
     response::Json(index.read().await.get_document(docid).map(Cow::into_owned))
 }
 
-async fn get_documents<I: RawIndex>(
-    extract::Extension(index): extract::Extension<Index<I>>,
-) -> response::Json<Vec<Document>> {
-    response::Json(
-        index
-            .read()
-            .await
-            .get_documents()
-            .into_iter()
-            .map(Cow::into_owned)
-            .collect(),
-    )
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarQuery {
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default = "DEFAULT_LIMIT")]
+    pub limit: usize,
+    pub filter: Option<String>,
+    /// Attach the similarity of each document returned, between 0 and 1.
+    #[serde(default)]
+    pub show_ranking_score: bool,
+}
+
+async fn similar_documents<I: RawIndex>(
+    Handle { index, .. }: Handle<I>,
+    extract::Path(DocumentPath { docid }): extract::Path<DocumentPath>,
+    extract::Query(query): extract::Query<SimilarQuery>,
+) -> Result<response::Json<Value>, (StatusCode, String)> {
+    let now = Instant::now();
+
+    let index = index.read().await;
+    let results = index
+        .similar(docid, query.filter.as_deref())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!("Document `{docid}` not found."),
+        ))?;
+    let total_hits = results.len();
+    let results: Vec<_> = results
+        .into_iter()
+        .skip(query.offset)
+        .take(query.limit)
+        .filter_map(|hit| {
+            let mut document = index.get_document(hit.docid)?.into_owned();
+            document.0.remove(VECTORS_FIELD);
+            if query.show_ranking_score {
+                document
+                    .0
+                    .insert("_rankingScore".to_string(), json!(hit.score));
+            }
+            Some(document)
+        })
+        .collect();
+
+    Ok(response::Json(
+        json!({ "elapsed": format!("{:?}", now.elapsed()), "nb_hits": results.len(), "totalHits": total_hits, "results": results }),
+    ))
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct DocumentsQuery {
+    #[serde(default)]
+    pub offset: usize,
+    /// All the documents are returned without limit.
+    pub limit: Option<usize>,
+    /// Only return the documents matching this filter, see `Filter`.
+    pub filter: Option<String>,
+    /// Only return these attributes of the documents, separated by commas.
+    pub fields: Option<String>,
+}
+
+async fn get_documents<I: RawIndex + 'static>(
+    Handle { index, .. }: Handle<I>,
+    headers: HeaderMap,
+    extract::Query(query): extract::Query<DocumentsQuery>,
+) -> Result<response::Response, (StatusCode, String)> {
+    let format = Format::from_header(headers.get(header::ACCEPT));
+    let filter = query
+        .filter
+        .as_deref()
+        .map(Filter::parse)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let guard = index.read().await;
+    let mut page = DocumentsPage {
+        docids: filter.map(|filter| {
+            filter
+                .docids(&*guard)
+                .into_iter()
+                .collect::<Vec<_>>()
+                .into_iter()
+        }),
+        after: None,
+        offset: query.offset,
+        limit: query.limit.unwrap_or(usize::MAX),
+        fields: query.fields.clone(),
+    };
+    drop(guard);
+
+    Ok(stream_documents(
+        index,
+        format,
+        query.fields,
+        move |index| page.next_chunk(index),
+    ))
+}
+
+/// The documents of a page of `GET /documents`, they are read chunk by chunk.
+struct DocumentsPage {
+    /// The documents matching the filter, the documents are read from the index without filter.
+    docids: Option<std::vec::IntoIter<DocId>>,
+    /// The last document read from the index.
+    after: Option<DocId>,
+    offset: usize,
+    limit: usize,
+    fields: Option<String>,
+}
+
+impl DocumentsPage {
+    /// The next documents of the page, `None` once they have all been returned.
+    fn next_chunk<I: RawIndex>(&mut self, index: &I) -> Option<Vec<Document>> {
+        let mut chunk = Vec::new();
+        while chunk.is_empty() && self.limit > 0 {
+            let documents: Vec<_> = match &mut self.docids {
+                Some(docids) => {
+                    let docids: Vec<_> = docids.take(CHUNK_SIZE).collect();
+                    if docids.is_empty() {
+                        return None;
+                    }
+                    // the documents deleted since the filter was evaluated are skipped
+                    docids
+                        .into_iter()
+                        .filter_map(|docid| index.get_document(docid))
+                        .collect()
+                }
+                None => {
+                    let documents = index.documents_after(self.after, CHUNK_SIZE);
+                    self.after = Some(documents.last()?.docid());
+                    documents
+                }
+            };
+            for document in documents {
+                if self.offset > 0 {
+                    self.offset -= 1;
+                    continue;
+                }
+                if self.limit == 0 {
+                    break;
+                }
+                self.limit -= 1;
+                let mut document = document.into_owned();
+                if let Some(fields) = &self.fields {
+                    document.retain_fields(fields);
+                }
+                chunk.push(document);
+            }
+        }
+        (!chunk.is_empty()).then_some(chunk)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -70,6 +370,12 @@ pub enum OneOrMany<T> {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Document(serde_json::Map<String, serde_json::Value>);
 
+impl From<serde_json::Map<String, serde_json::Value>> for Document {
+    fn from(attributes: serde_json::Map<String, serde_json::Value>) -> Self {
+        Document(attributes)
+    }
+}
+
 impl Document {
     pub fn docid(&self) -> DocId {
         for (field, value) in self.0.iter() {
@@ -88,6 +394,26 @@ impl Document {
         );
     }
 
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        self.0.get(field)
+    }
+
+    /// The attributes of the document with their values, in their order.
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.0.iter().map(|(field, value)| (field.as_str(), value))
+    }
+
+    /// Only keep the attributes in a list of attributes separated by commas, like `title,year`.
+    pub fn retain_fields(&mut self, fields: &str) {
+        let fields: Vec<_> = fields.split(',').map(str::trim).collect();
+        self.0.retain(|field, _| fields.contains(&field.as_str()));
+    }
+
+    /// The strings of one attribute, nested in arrays and objects or not.
+    pub fn field(&self, field: &str) -> impl Iterator<Item = &str> {
+        self.0.get(field).into_iter().flat_map(Self::_fields)
+    }
+
     pub fn fields(&self) -> impl Iterator<Item = &str> {
         self.0.values().flat_map(|value| match value {
             Value::String(s) => {
@@ -115,23 +441,112 @@ impl Document {
     }
 }
 
-async fn add_documents<I: RawIndex>(
-    extract::Extension(index): extract::Extension<Index<I>>,
-    extract::Json(documents): extract::Json<OneOrMany<Document>>,
-) -> response::Json<Value> {
+/// The number of documents indexed at once, the index is locked while they are indexed.
+const INDEXING_CHUNK: usize = 10_000;
+/// The number of chunks of a body received but not parsed yet.
+const BODY_CHUNKS: usize = 64;
+
+/// Add documents sent in JSON, NDJSON or CSV depending on the `Content-Type` header.
+/// The body is parsed while it's received and the documents are indexed by chunks.
+async fn add_documents<I: RawIndex + 'static>(
+    Handle { index, suggester }: Handle<I>,
+    headers: HeaderMap,
+    extract::RawBody(mut body): extract::RawBody,
+) -> Result<response::Json<Value>, (StatusCode, String)> {
     let now = Instant::now();
 
-    let mut index = index.write().await;
-    match documents {
-        OneOrMany::One(document) => index.add_documents(vec![document]),
-        OneOrMany::Multiple(documents) => index.add_documents(documents),
+    let format = Format::from_header(headers.get(header::CONTENT_TYPE));
+    let (sender, receiver) = mpsc::channel(BODY_CHUNKS);
+    let indexer = {
+        let index = index.clone();
+        tokio::task::spawn_blocking(move || {
+            let documents = read_documents(format, BodyReader::new(receiver));
+            index_documents(&index, documents)
+        })
+    };
+    while let Some(data) = body.data().await {
+        let data = data.map_err(io::Error::other);
+        // the indexer stopped on an invalid document
+        if sender.send(data).await.is_err() {
+            break;
+        }
     }
+    drop(sender);
+    let indexed = indexer.await.expect("The indexing panicked");
+    suggester.invalidate();
+    indexed.map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
-    response::Json(json!({ "elapsed": format!("{:?}", now.elapsed()) }))
+    Ok(response::Json(
+        json!({ "elapsed": format!("{:?}", now.elapsed()) }),
+    ))
+}
+
+/// Index the documents by chunks of `INDEXING_CHUNK`, the index is only locked while a chunk is
+/// indexed. Stops at the first invalid document, the chunks before it stay indexed.
+fn index_documents<I: RawIndex>(
+    index: &Index<I>,
+    documents: impl Iterator<Item = Result<Document, String>>,
+) -> Result<(), String> {
+    let mut nb_documents = 0;
+    let mut chunk = Vec::with_capacity(INDEXING_CHUNK);
+    let mut documents = documents.peekable();
+    while let Some(document) = documents.next() {
+        let failed = |e| match nb_documents {
+            0 => e,
+            nb_documents => format!("{e}, the {nb_documents} documents before it were added."),
+        };
+        chunk.push(document.map_err(failed)?);
+        if chunk.len() < INDEXING_CHUNK && documents.peek().is_some() {
+            continue;
+        }
+
+        let mut index = index.blocking_write();
+        if let Some(dimensions) = index.settings().vector_dimensions {
+            for document in &chunk {
+                if let Some(vector) = document.get(VECTORS_FIELD) {
+                    parse_vector(vector, dimensions)
+                        .map_err(|e| failed(format!("Document {}: {e}", document.docid())))?;
+                }
+            }
+        }
+        nb_documents += chunk.len();
+        index.add_documents(std::mem::take(&mut chunk));
+    }
+    Ok(())
+}
+
+/// Read the chunks of a body sent by the handler receiving it, from a blocking thread.
+struct BodyReader {
+    receiver: mpsc::Receiver<io::Result<Bytes>>,
+    chunk: Bytes,
+}
+
+impl BodyReader {
+    fn new(receiver: mpsc::Receiver<io::Result<Bytes>>) -> Self {
+        BodyReader {
+            receiver,
+            chunk: Bytes::new(),
+        }
+    }
+}
+
+impl Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.chunk.is_empty() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => self.chunk = chunk?,
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len());
+        buf[..len].copy_from_slice(&self.chunk[..len]);
+        self.chunk = self.chunk.slice(len..);
+        Ok(len)
+    }
 }
 
 async fn delete_documents<I: RawIndex>(
-    extract::Extension(index): extract::Extension<Index<I>>,
+    Handle { index, suggester }: Handle<I>,
     extract::Json(docids): extract::Json<OneOrMany<DocId>>,
 ) -> response::Json<Value> {
     let now = Instant::now();
@@ -141,6 +556,7 @@ async fn delete_documents<I: RawIndex>(
         OneOrMany::One(docid) => index.delete_documents(vec![docid]),
         OneOrMany::Multiple(docids) => index.delete_documents(docids),
     }
+    suggester.invalidate();
 
     response::Json(json!({ "elapsed": format!("{:?}", now.elapsed()) }))
 }
@@ -148,26 +564,705 @@ async fn delete_documents<I: RawIndex>(
 const DEFAULT_LIMIT: fn() -> usize = || 20;
 
 #[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct Query {
     pub q: Option<String>,
+    #[serde(default)]
+    pub offset: usize,
     #[serde(default = "DEFAULT_LIMIT")]
     pub limit: usize,
+    /// Attach the score of each document returned, between 0 and 1.
+    #[serde(default)]
+    pub show_ranking_score: bool,
+    /// Attach the score of each ranking rule to the documents returned.
+    #[serde(default)]
+    pub show_ranking_score_details: bool,
+    /// The number of words of the query a document must match.
+    pub minimum_should_match: Option<MinimumShouldMatch>,
+    /// Drop the documents with a `_rankingScore` below this one.
+    pub ranking_score_threshold: Option<f64>,
+    /// Only keep the best document for each value of this attribute, instead of the one of the settings.
+    pub distinct: Option<String>,
+    /// Only return the documents matching this filter, see `Filter`.
+    pub filter: Option<String>,
+    /// Sort the documents by their distance to a point, like `_geoPoint(48.85, 2.35):asc`.
+    pub sort: Option<String>,
+    /// Count the documents found in buckets of numeric faceted attributes, like `year:10,price:100`.
+    pub histogram: Option<String>,
+    /// Search the documents whose `_vectors` are the closest to this embedding,
+    /// either an array or a string of numbers separated by commas.
+    #[serde(default, deserialize_with = "deserialize_vector")]
+    pub vector: Option<Vec<f32>>,
+    /// The weight of the vector search when the query has both words and a vector, 0.5 by default.
+    pub semantic_ratio: Option<f64>,
+    /// Keep the `_vectors` of the documents returned.
+    #[serde(default)]
+    pub retrieve_vectors: bool,
+    /// Only return these attributes of the documents, separated by commas.
+    pub fields: Option<String>,
 }
 
-async fn search<I: RawIndex>(
-    extract::Extension(index): extract::Extension<Index<I>>,
+impl Default for Query {
+    fn default() -> Self {
+        Query {
+            q: None,
+            offset: 0,
+            limit: DEFAULT_LIMIT(),
+            show_ranking_score: false,
+            show_ranking_score_details: false,
+            minimum_should_match: None,
+            ranking_score_threshold: None,
+            distinct: None,
+            filter: None,
+            sort: None,
+            histogram: None,
+            vector: None,
+            semantic_ratio: None,
+            retrieve_vectors: false,
+            fields: None,
+        }
+    }
+}
+
+/// A number of words, like `2`, or a percentage of the words of the query, like `75%`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MinimumShouldMatch {
+    Count(usize),
+    Percentage(f64),
+}
+
+impl MinimumShouldMatch {
+    /// The number of words a document must match among the words of the query,
+    /// a percentage is rounded down.
+    pub fn required(&self, nb_words: usize) -> usize {
+        match self {
+            MinimumShouldMatch::Count(count) => (*count).min(nb_words),
+            MinimumShouldMatch::Percentage(percentage) => {
+                (nb_words as f64 * percentage / 100.0).floor() as usize
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MinimumShouldMatch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = MinimumShouldMatch;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number of words like `2` or a percentage like `75%`")
+            }
+
+            fn visit_u64<E: de::Error>(self, count: u64) -> Result<Self::Value, E> {
+                Ok(MinimumShouldMatch::Count(count as usize))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                let invalid = || E::invalid_value(de::Unexpected::Str(value), &self);
+                match value.strip_suffix('%') {
+                    Some(percentage) => match percentage.trim().parse::<f64>() {
+                        Ok(percentage) if (0.0..=100.0).contains(&percentage) => {
+                            Ok(MinimumShouldMatch::Percentage(percentage))
+                        }
+                        _ => Err(invalid()),
+                    },
+                    None => value
+                        .trim()
+                        .parse()
+                        .map(MinimumShouldMatch::Count)
+                        .map_err(|_| invalid()),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+fn deserialize_vector<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<f32>>, D::Error> {
+    struct Visitor;
+
+    impl<'de> de::Visitor<'de> for Visitor {
+        type Value = Vec<f32>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an array of numbers or numbers separated by commas like `0.1,0.8`")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            value
+                .split(',')
+                .map(|number| number.trim().parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut vector = Vec::new();
+            while let Some(number) = seq.next_element()? {
+                vector.push(number);
+            }
+            Ok(vector)
+        }
+    }
+
+    deserializer.deserialize_any(Visitor).map(Some)
+}
+
+async fn search<I: RawIndex + 'static>(
+    Handle { index, suggester }: Handle<I>,
+    headers: HeaderMap,
     extract::Query(query): extract::Query<Query>,
-) -> response::Json<Value> {
+) -> Result<response::Response, (StatusCode, String)> {
+    let format = Format::from_header(headers.get(header::ACCEPT));
+    let guard = index.read().await;
+    if format == Format::Json {
+        return search_response(&*guard, &suggester, &query)
+            .map(|response| response::Json(response).into_response())
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()));
+    }
+
+    let hits: Vec<_> = guard
+        .search(&query)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+        .into_iter()
+        .skip(query.offset)
+        .take(query.limit)
+        .collect();
+    drop(guard);
+
+    let fields = query.fields.clone();
+    let mut hits = hits.into_iter();
+    Ok(stream_documents(index, format, fields, move |index| {
+        let chunk: Vec<_> = hits.by_ref().take(CHUNK_SIZE).collect();
+        (!chunk.is_empty()).then(|| {
+            chunk
+                .into_iter()
+                .filter_map(|hit| hit_document(index, &query, hit).map(Cow::into_owned))
+                .collect()
+        })
+    }))
+}
+
+/// The number of documents fetched at once when streaming a response.
+const CHUNK_SIZE: usize = 100;
+
+/// Stream documents in a JSON array, in NDJSON or in CSV. They are fetched chunk by chunk by
+/// `next`, until it returns `None`, and the index is only locked while it runs so a slow client
+/// doesn't block the updates. The columns of the CSV are the `fields`, or the attributes of the
+/// documents of the first chunk.
+fn stream_documents<I, F>(
+    index: Index<I>,
+    format: Format,
+    fields: Option<String>,
+    mut next: F,
+) -> response::Response
+where
+    I: RawIndex + 'static,
+    F: FnMut(&I) -> Option<Vec<Document>> + Send + 'static,
+{
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let mut columns: Option<Vec<_>> = fields.map(|fields| {
+            fields
+                .split(',')
+                .map(|field| field.trim().to_string())
+                .collect()
+        });
+        let header = match format {
+            Format::Json => b"[".to_vec(),
+            Format::Ndjson => Vec::new(),
+            Format::Csv => columns.as_deref().map_or_else(Vec::new, write_csv_header),
+        };
+        if sender.send_data(header.into()).await.is_err() {
+            return;
+        }
+        let mut first = true;
+        loop {
+            let documents = match next(&*index.read().await) {
+                Some(documents) => documents,
+                None => break,
+            };
+            let chunk = match format {
+                Format::Json => write_json(&documents, first),
+                Format::Ndjson => write_ndjson(&documents),
+                Format::Csv => {
+                    let mut chunk = Vec::new();
+                    if columns.is_none() {
+                        let mut first_columns = Vec::new();
+                        for document in &documents {
+                            extend_columns(&mut first_columns, document);
+                        }
+                        chunk = write_csv_header(&first_columns);
+                        columns = Some(first_columns);
+                    }
+                    chunk.extend(write_csv(
+                        columns.as_deref().unwrap_or_default(),
+                        &documents,
+                    ));
+                    chunk
+                }
+            };
+            first = false;
+            // the client is gone
+            if sender.send_data(chunk.into()).await.is_err() {
+                return;
+            }
+        }
+        if format == Format::Json {
+            let _ = sender.send_data("]".into()).await;
+        }
+    });
+    http::Response::builder()
+        .header(header::CONTENT_TYPE, format.content_type())
+        .body(body)
+        .expect("Invalid response")
+        .into_response()
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct MultiSearch {
+    /// The queries, each one like the parameters of `/search`.
+    pub queries: Vec<Value>,
+    /// Merge the hits of all the queries in a single list instead of returning one list per query.
+    pub federation: Option<Federation>,
+}
+
+/// The page of the merged list of a federated multi-search, the `offset` and `limit`
+/// of the queries are ignored.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Federation {
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default = "DEFAULT_LIMIT")]
+    pub limit: usize,
+    /// The ranking scores of the hits of each index, by uid, are multiplied by this weight
+    /// and by the weight of their query before being merged.
+    #[serde(default)]
+    pub index_weights: HashMap<String, f64>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FederatedQuery {
+    #[serde(flatten)]
+    query: Query,
+    #[serde(default)]
+    federation_options: FederationOptions,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct FederationOptions {
+    /// The ranking scores of the hits of the query are multiplied by this weight before being merged.
+    #[serde(default = "DEFAULT_WEIGHT")]
+    pub weight: f64,
+}
+
+const DEFAULT_WEIGHT: fn() -> f64 = || 1.0;
+
+impl Default for FederationOptions {
+    fn default() -> Self {
+        FederationOptions {
+            weight: DEFAULT_WEIGHT(),
+        }
+    }
+}
+
+/// Run several searches at once, each one on the index named by its `indexUid` or on the default
+/// index. They all see the same version of their index since the indexes they search can't be
+/// updated while they run. A query that fails gets an `error` instead of its results.
+async fn multi_search<I: RawIndex>(
+    extract::Extension(indexes): extract::Extension<Arc<Indexes<I>>>,
+    extract::Json(multi_search): extract::Json<MultiSearch>,
+) -> Result<response::Json<Value>, (StatusCode, String)> {
     let now = Instant::now();
 
-    let index = index.read().await;
-    let results = index.search(&query);
+    let uids: BTreeSet<_> = multi_search
+        .queries
+        .iter()
+        .filter_map(|query| index_uid(query).ok())
+        .collect();
+    let handles: BTreeMap<_, _> = uids
+        .into_iter()
+        .filter_map(|uid| Some((uid.to_string(), indexes.get(uid)?)))
+        .collect();
+    // the indexes are locked in the order of their uids, two batches can't wait for each other
+    let mut snapshots = BTreeMap::new();
+    for (uid, handle) in &handles {
+        snapshots.insert(
+            uid.as_str(),
+            Snapshot {
+                uid,
+                index: handle.index.read().await,
+                suggester: &handle.suggester,
+            },
+        );
+    }
+    let snapshot = |query: &Value| {
+        let uid = index_uid(query)?;
+        snapshots
+            .get(uid)
+            .ok_or_else(|| format!("Index `{uid}` not found."))
+    };
+
+    if let Some(federation) = multi_search.federation {
+        for (uid, weight) in &federation.index_weights {
+            if !weight.is_finite() || *weight < 0.0 {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "Invalid weight `{weight}` for the index `{uid}`, expected a positive number."
+                    ),
+                ));
+            }
+        }
+        let queries = multi_search
+            .queries
+            .into_iter()
+            .enumerate()
+            .map(|(position, query)| {
+                let snapshot =
+                    snapshot(&query).map_err(|e| format!("Invalid query {position}: {e}"))?;
+                let FederatedQuery {
+                    query,
+                    federation_options,
+                } = serde_json::from_value(query)
+                    .map_err(|e| format!("Invalid query {position}: {e}"))?;
+                let weight = federation_options.weight;
+                if !weight.is_finite() || weight < 0.0 {
+                    return Err(format!(
+                        "Invalid weight `{weight}` for the query {position}, expected a positive number."
+                    ));
+                }
+                let index_weight = federation.index_weights.get(snapshot.uid);
+                Ok((snapshot, query, weight * index_weight.unwrap_or(&DEFAULT_WEIGHT())))
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        return federated_response(&federation, queries)
+            .map(response::Json)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e));
+    }
+
+    let results: Vec<_> = multi_search
+        .queries
+        .into_iter()
+        .map(|query| {
+            let response = snapshot(&query).and_then(|snapshot| {
+                let query =
+                    serde_json::from_value(query).map_err(|e| format!("Invalid query: {e}"))?;
+                search_response(&*snapshot.index, snapshot.suggester, &query)
+                    .map_err(|e| e.to_string())
+            });
+            response.unwrap_or_else(|error| json!({ "error": error }))
+        })
+        .collect();
+
+    Ok(response::Json(
+        json!({ "elapsed": format!("{:?}", now.elapsed()), "results": results }),
+    ))
+}
+
+/// An index read by a multi-search, it stays locked until all the queries ran.
+struct Snapshot<'a, I> {
+    uid: &'a str,
+    index: RwLockReadGuard<'a, I>,
+    suggester: &'a Suggester,
+}
+
+/// The uid of the index searched by a query of a multi-search, the default index if it has none.
+fn index_uid(query: &Value) -> Result<&str, String> {
+    match query.get("indexUid") {
+        None => Ok(DEFAULT_INDEX),
+        Some(Value::String(uid)) => Ok(uid),
+        Some(uid) => Err(format!("Invalid `indexUid` {uid}, expected a string.")),
+    }
+}
+
+/// Merge the hits of several queries, on one index or several, by their ranking score times the
+/// weight of their query and of their index, after the hits pinned by the curation rules of the
+/// queries. The ranking scores are comparable across the indexes since each index normalizes them
+/// between 0 and 1 against its own bounds. A document found by several queries is only kept for
+/// the one giving it the best place, and carries the uid of its index and the position of this
+/// query in `_federation`.
+fn federated_response<I: RawIndex>(
+    federation: &Federation,
+    queries: Vec<(&Snapshot<I>, Query, f64)>,
+) -> Result<Value, String> {
+    let now = Instant::now();
+
+    let mut hits = Vec::new();
+    for (position, (snapshot, query, weight)) in queries.iter().enumerate() {
+        // every query must return enough hits to fill the merged page on its own
+        let query = Query {
+            offset: 0,
+            limit: federation.offset + federation.limit,
+            ..query.clone()
+        };
+        let results = snapshot
+            .index
+            .search(&query)
+            .map_err(|e| format!("Invalid query {position}: {e}"))?;
+        hits.extend(
+            results
+                .into_iter()
+                .map(|hit| (hit.score * weight, position, hit)),
+        );
+    }
+    // the sort is stable, the hits of a query stay in its order
+    hits.sort_by(
+        |(left, left_position, left_hit), (right, right_position, right_hit)| {
+            match (left_hit.pinned, right_hit.pinned) {
+                // the pinned hits keep the order of their rules, whatever their score
+                (true, true) => left_position.cmp(right_position),
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                (false, false) => right
+                    .total_cmp(left)
+                    .then(left_position.cmp(right_position)),
+            }
+        },
+    );
+    // the same docid can be another document in another index
+    let mut seen = HashSet::new();
+    hits.retain(|(_, position, hit)| seen.insert((queries[*position].0.uid, hit.docid)));
+    let total_hits = hits.len();
+
+    let results: Vec<_> = hits
+        .into_iter()
+        .skip(federation.offset)
+        .take(federation.limit)
+        .filter_map(|(score, position, hit)| {
+            let (snapshot, query, _) = &queries[position];
+            let mut document = hit_document(&*snapshot.index, query, hit)?.into_owned();
+            document.0.insert(
+                "_federation".to_string(),
+                json!({ "indexUid": snapshot.uid, "queriesPosition": position, "weightedRankingScore": score }),
+            );
+            Some(document)
+        })
+        .collect();
+
+    Ok(
+        json!({ "elapsed": format!("{:?}", now.elapsed()), "nb_hits": results.len(), "totalHits": total_hits, "results": results }),
+    )
+}
+
+/// The document of a hit with the extra attributes asked by the query.
+fn hit_document<'a, I: RawIndex>(
+    index: &'a I,
+    query: &Query,
+    hit: Hit,
+) -> Option<Cow<'a, Document>> {
+    let document = index.get_document(hit.docid)?;
+    let has_vectors = document.get(VECTORS_FIELD).is_some();
+    if !query.show_ranking_score
+        && !query.show_ranking_score_details
+        && !hit.pinned
+        && hit.geo_distance.is_none()
+        && (query.retrieve_vectors || !has_vectors)
+        && query.fields.is_none()
+    {
+        return Some(document);
+    }
+    let mut document = document.into_owned();
+    if let Some(fields) = &query.fields {
+        document.retain_fields(fields);
+    }
+    if query.show_ranking_score || query.show_ranking_score_details {
+        document
+            .0
+            .insert("_rankingScore".to_string(), json!(hit.score));
+    }
+    if query.show_ranking_score_details {
+        document
+            .0
+            .insert("_rankingScoreDetails".to_string(), json!(hit.details));
+        if let Some(score) = hit.semantic_score {
+            document
+                .0
+                .insert("_semanticScore".to_string(), json!(score));
+        }
+    }
+    if !query.retrieve_vectors {
+        document.0.remove(VECTORS_FIELD);
+    }
+    if hit.pinned {
+        document.0.insert("_pinned".to_string(), json!(true));
+    }
+    if let Some(distance) = hit.geo_distance {
+        document
+            .0
+            .insert("_geoDistance".to_string(), json!(distance.round() as u64));
+    }
+    Some(Cow::Owned(document))
+}
+
+/// The response of a search, with the documents of the page and their extra attributes.
+fn search_response<I: RawIndex>(
+    index: &I,
+    suggester: &Suggester,
+    query: &Query,
+) -> Result<Value, SearchError> {
+    let now = Instant::now();
+
+    let results = index.search(query)?;
+    let total_hits = results.len();
+    let histograms = query
+        .histogram
+        .as_deref()
+        .map(|histogram| histograms(index, &index.settings(), histogram, &results))
+        .transpose()?;
+    if let (Some(q), false) = (&query.q, results.is_empty()) {
+        let tokenizer = index.tokenizer(&index.settings());
+        suggester.record(tokenizer.words(q).collect());
+    }
     let results: Vec<_> = results
         .into_iter()
-        .map(|id| index.get_document(id))
+        .skip(query.offset)
         .take(query.limit)
+        .filter_map(|hit| hit_document(index, query, hit))
         .collect();
 
-    let response = json!({ "elapsed": format!("{:?}", now.elapsed()), "nb_hits": results.len(), "results": results });
-    response::Json(response)
+    let mut response = json!({ "elapsed": format!("{:?}", now.elapsed()), "nb_hits": results.len(), "totalHits": total_hits, "results": results });
+    if let Some(histograms) = histograms {
+        response["histograms"] = json!(histograms);
+    }
+    if let (Some(q), 0) = (&query.q, total_hits) {
+        let tokenizer = index.tokenizer(&index.settings());
+        let words: Vec<_> = tokenizer.words(q).collect();
+        let corrected: Vec<_> = words
+            .iter()
+            .map(|word| {
+                suggester
+                    .correct(index, word)
+                    .unwrap_or_else(|| word.clone())
+            })
+            .collect();
+        if corrected != words {
+            response["suggestion"] = json!(corrected.join(" "));
+        }
+    }
+    Ok(response)
+}
+
+const DEFAULT_SUGGESTIONS: fn() -> usize = || 10;
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SuggestQuery {
+    pub q: String,
+    #[serde(default = "DEFAULT_SUGGESTIONS")]
+    pub limit: usize,
+    pub filter: Option<String>,
+}
+
+async fn suggest<I: RawIndex>(
+    Handle { index, suggester }: Handle<I>,
+    extract::Query(query): extract::Query<SuggestQuery>,
+) -> Result<response::Json<Value>, (StatusCode, String)> {
+    let now = Instant::now();
+
+    let filter = query
+        .filter
+        .as_deref()
+        .map(Filter::parse)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let index = index.read().await;
+    // only the last word of the query is completed
+    let tokenizer = index.tokenizer(&index.settings());
+    let prefix = tokenizer.words(&query.q).last().unwrap_or_default();
+    let candidates = filter.map(|filter| filter.docids(&*index));
+    let suggestions = suggester.suggest(&*index, &prefix, candidates.as_ref(), query.limit);
+
+    Ok(response::Json(
+        json!({ "elapsed": format!("{:?}", now.elapsed()), "suggestions": suggestions }),
+    ))
+}
+
+async fn get_settings<I: RawIndex>(Handle { index, .. }: Handle<I>) -> response::Json<Settings> {
+    response::Json(index.read().await.settings())
+}
+
+async fn update_settings<I: RawIndex>(
+    Handle { index, suggester }: Handle<I>,
+    extract::Json(update): extract::Json<serde_json::Map<String, Value>>,
+) -> Result<response::Json<Value>, (StatusCode, String)> {
+    let now = Instant::now();
+
+    let mut index = index.write().await;
+    let settings = index
+        .settings()
+        .update(update)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    index.update_settings(settings);
+    suggester.invalidate();
+
+    Ok(response::Json(
+        json!({ "elapsed": format!("{:?}", now.elapsed()) }),
+    ))
+}
+
+async fn get_rules<I: RawIndex>(Handle { index, .. }: Handle<I>) -> response::Json<Rules> {
+    response::Json(index.read().await.curation_rules())
+}
+
+async fn get_rule<I: RawIndex>(
+    Handle { index, .. }: Handle<I>,
+    extract::Path(RulePath { id }): extract::Path<RulePath>,
+) -> Result<response::Json<Rule>, StatusCode> {
+    let mut rules = index.read().await.curation_rules();
+    rules
+        .remove(&id)
+        .map(response::Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn put_rule<I: RawIndex>(
+    Handle { index, .. }: Handle<I>,
+    extract::Path(RulePath { id }): extract::Path<RulePath>,
+    extract::Json(rule): extract::Json<Rule>,
+) -> Result<response::Json<Value>, (StatusCode, String)> {
+    let now = Instant::now();
+
+    let mut index = index.write().await;
+    let tokenizer = index.tokenizer(&index.settings());
+    if tokenizer.words(&rule.query).next().is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "The query `{}` of the rule doesn't contain any word",
+                rule.query
+            ),
+        ));
+    }
+    let mut rules = index.curation_rules();
+    rules.insert(id, rule);
+    index.put_curation_rules(rules);
+
+    Ok(response::Json(
+        json!({ "elapsed": format!("{:?}", now.elapsed()) }),
+    ))
+}
+
+async fn delete_rule<I: RawIndex>(
+    Handle { index, .. }: Handle<I>,
+    extract::Path(RulePath { id }): extract::Path<RulePath>,
+) -> Result<response::Json<Value>, StatusCode> {
+    let now = Instant::now();
+
+    let mut index = index.write().await;
+    let mut rules = index.curation_rules();
+    rules.remove(&id).ok_or(StatusCode::NOT_FOUND)?;
+    index.put_curation_rules(rules);
+
+    Ok(response::Json(
+        json!({ "elapsed": format!("{:?}", now.elapsed()) }),
+    ))
 }
@@ -0,0 +1,137 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+
+use crate::{DocId, Hit, Index, Tokenizer};
+
+/// The curation rules of an index, by id.
+pub type Rules = BTreeMap<String, Rule>;
+
+/// Pin some documents at the top of the results of a query and hide others.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    /// The query triggering the rule, it's normalized like the queries.
+    pub query: String,
+    #[serde(default)]
+    pub matching: Matching,
+    /// The documents pinned at the top of the results, in this order.
+    #[serde(default)]
+    pub promote: Vec<DocId>,
+    /// The documents removed from the results.
+    #[serde(default)]
+    pub hide: Vec<DocId>,
+    /// The rule is only applied from this unix timestamp, in seconds.
+    pub active_from: Option<u64>,
+    /// The rule is only applied until this unix timestamp, in seconds.
+    pub active_until: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Matching {
+    /// The query must be the same as the query of the rule.
+    #[default]
+    Exact,
+    /// The query must contain the words of the rule next to each other.
+    Contains,
+}
+
+impl Rule {
+    fn is_active(&self, now: u64) -> bool {
+        self.active_from.is_none_or(|from| from <= now)
+            && self.active_until.is_none_or(|until| now < until)
+    }
+
+    fn matches(&self, query: &[String], tokenizer: &dyn Tokenizer) -> bool {
+        let words: Vec<_> = tokenizer.words(&self.query).collect();
+        if words.is_empty() {
+            return false;
+        }
+        match self.matching {
+            Matching::Exact => words == query,
+            Matching::Contains => query.windows(words.len()).any(|window| window == words),
+        }
+    }
+}
+
+/// The documents pinned and hidden by all the active rules matching a query.
+pub struct Curation {
+    promote: Vec<DocId>,
+    hide: HashSet<DocId>,
+}
+
+impl Curation {
+    pub fn new(rules: &Rules, query: &str, tokenizer: &dyn Tokenizer) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let query: Vec<_> = tokenizer.words(query).collect();
+
+        let mut curation = Curation {
+            promote: Vec::new(),
+            hide: HashSet::new(),
+        };
+        if query.is_empty() {
+            return curation;
+        }
+        for rule in rules.values() {
+            if rule.is_active(now) && rule.matches(&query, tokenizer) {
+                curation.promote.extend(&rule.promote);
+                curation.hide.extend(&rule.hide);
+            }
+        }
+        curation
+    }
+
+    /// Move the pinned documents at the top of the hits, even if they don't match the query,
+    /// and remove the hidden ones. A document both pinned and hidden is hidden.
+    /// The pinned documents missing from the hits are only added if they match the `filter`
+    /// and if their score of 0 isn't below the `threshold`.
+    pub fn apply<I: Index>(
+        &self,
+        index: &I,
+        mut hits: Vec<Hit>,
+        filter: Option<&RoaringBitmap>,
+        threshold: Option<f64>,
+    ) -> Vec<Hit> {
+        let mut pinned: Vec<Hit> = Vec::new();
+
+        for &docid in &self.promote {
+            if self.hide.contains(&docid) || pinned.iter().any(|hit| hit.docid == docid) {
+                continue;
+            }
+            let hit = match hits.iter().position(|hit| hit.docid == docid) {
+                Some(position) => hits.remove(position),
+                None if filter.is_none_or(|filter| filter.contains(docid))
+                    && threshold.is_none_or(|threshold| threshold <= 0.0)
+                    && index.get_document(docid).is_some() =>
+                {
+                    Hit {
+                        docid,
+                        score: 0.0,
+                        details: None,
+                        pinned: false,
+                        geo_distance: None,
+                        semantic_score: None,
+                    }
+                }
+                // the document was deleted since the rule was written, or is filtered out
+                None => continue,
+            };
+            pinned.push(Hit {
+                pinned: true,
+                ..hit
+            });
+        }
+
+        hits.retain(|hit| !self.hide.contains(&hit.docid));
+        pinned.extend(hits);
+        pinned
+    }
+}
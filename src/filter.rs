@@ -0,0 +1,476 @@
+//! A small filter language evaluated against the fields of the documents,
+//! like `genre = horror AND NOT (year > 1984 OR director != "john carpenter")`.
+//! The numbers and the dates are compared with `>`, `>=`, `<` and `<=`.
+//! The position of the documents is filtered with `_geoRadius(lat, lng, meters)` and
+//! `_geoBoundingBox([north, west], [south, east])`.
+
+use std::{
+    collections::BTreeSet,
+    iter::Peekable,
+    ops::{Bound, RangeBounds},
+    str::Chars,
+};
+
+use roaring::RoaringBitmap;
+use serde_json::Value;
+
+use crate::{
+    geo::{self, BoundingBox, Point, GEO_FIELD},
+    numbers::{number_values, parse_number, NumberRange},
+    Document, Index,
+};
+
+/// The number of documents read at once when a filter can't use the index.
+const DOCUMENTS_CHUNK: usize = 1000;
+/// The maximum number of parentheses and `NOT` nested in a filter, the filters are parsed
+/// and evaluated recursively.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// The field is equal to the value, or contains it if it's an array.
+    /// The strings are compared without taking the case into account.
+    Equal {
+        field: String,
+        value: String,
+    },
+    /// One of the numbers or dates of the field is in the range.
+    Range {
+        field: String,
+        range: NumberRange,
+    },
+    /// The document is less than `meters` away from the center.
+    GeoRadius {
+        center: Point,
+        meters: f64,
+    },
+    GeoBoundingBox(BoundingBox),
+    Not(Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+impl Filter {
+    pub fn parse(filter: &str) -> Result<Self, String> {
+        let tokens = lex(filter)?;
+        let mut parser = Parser {
+            tokens,
+            current: 0,
+            depth: 0,
+        };
+        let filter = parser.or()?;
+        match parser.next() {
+            None => Ok(filter),
+            Some(token) => Err(format!("Unexpected `{token}` in the filter.")),
+        }
+    }
+
+    pub fn matches(&self, document: &Document) -> bool {
+        match self {
+            Filter::Equal { field, value } => document.get(field).is_some_and(|field| {
+                value_matches(field, value)
+                    || equal_number(value).is_some_and(|n| number_values(field).contains(&n))
+            }),
+            Filter::Range { field, range } => document
+                .get(field)
+                .is_some_and(|field| number_values(field).iter().any(|n| range.contains(n))),
+            Filter::GeoRadius { center, meters } => document
+                .get(GEO_FIELD)
+                .and_then(Point::from_value)
+                .is_some_and(|point| point.distance(center) <= *meters),
+            Filter::GeoBoundingBox(area) => document
+                .get(GEO_FIELD)
+                .and_then(Point::from_value)
+                .is_some_and(|point| area.contains(&point)),
+            Filter::Not(filter) => !filter.matches(document),
+            Filter::And(left, right) => left.matches(document) && right.matches(document),
+            Filter::Or(left, right) => left.matches(document) || right.matches(document),
+        }
+    }
+
+    /// The documents of the index matching the filter. The geo conditions use the geohash
+    /// cells and the faceted attributes use their values and their numbers, the other conditions
+    /// are checked on every document, or only on the documents matching the other side of an
+    /// `AND` when it can be evaluated from the index.
+    pub fn docids<I: Index>(&self, index: &I) -> RoaringBitmap {
+        let settings = index.settings();
+        self.evaluate(index, &settings.faceted())
+    }
+
+    fn evaluate<I: Index>(&self, index: &I, faceted: &BTreeSet<&str>) -> RoaringBitmap {
+        match self {
+            // the values of the facets are lowercased like the strings compared by `matches`,
+            // and the numbers are compared as numbers so `1984.0` equals `1984`
+            Filter::Equal { field, value } if faceted.contains(field.as_str()) => {
+                let docids = index.facet_docids(field, &value.to_lowercase());
+                match equal_number(value) {
+                    Some(n) => {
+                        docids
+                            | index.number_docids(field, (Bound::Included(n), Bound::Included(n)))
+                    }
+                    None => docids,
+                }
+            }
+            Filter::Range { field, range } if faceted.contains(field.as_str()) => {
+                index.number_docids(field, *range)
+            }
+            Filter::GeoRadius { center, meters } => geo::docids_around(index, center, *meters),
+            Filter::GeoBoundingBox(area) => geo::docids_in_box(index, area),
+            Filter::And(left, right) if self.uses_index(faceted) => {
+                match (left.uses_index(faceted), right.uses_index(faceted)) {
+                    (true, true) => left.evaluate(index, faceted) & right.evaluate(index, faceted),
+                    (true, false) => matching(index, left.evaluate(index, faceted), right),
+                    (false, _) => matching(index, right.evaluate(index, faceted), left),
+                }
+            }
+            Filter::Or(left, right) if self.uses_index(faceted) => {
+                left.evaluate(index, faceted) | right.evaluate(index, faceted)
+            }
+            // all the documents are listed but the condition isn't checked on each of them
+            Filter::Not(filter) if filter.uses_index(faceted) => {
+                let all: RoaringBitmap = index
+                    .documents(DOCUMENTS_CHUNK)
+                    .map(|document| document.docid())
+                    .collect();
+                all - filter.evaluate(index, faceted)
+            }
+            _ => index
+                .documents(DOCUMENTS_CHUNK)
+                .filter(|document| self.matches(document))
+                .map(|document| document.docid())
+                .collect(),
+        }
+    }
+
+    /// Whether a condition can be evaluated on its own, without reading all the documents.
+    fn uses_index(&self, faceted: &BTreeSet<&str>) -> bool {
+        match self {
+            Filter::Equal { field, .. } | Filter::Range { field, .. } => {
+                faceted.contains(field.as_str())
+            }
+            Filter::GeoRadius { .. } | Filter::GeoBoundingBox(_) => true,
+            Filter::And(left, right) | Filter::Or(left, right) => {
+                left.uses_index(faceted) || right.uses_index(faceted)
+            }
+            Filter::Not(_) => false,
+        }
+    }
+}
+
+/// Only keep the candidates whose document matches the filter.
+fn matching<I: Index>(index: &I, candidates: RoaringBitmap, filter: &Filter) -> RoaringBitmap {
+    candidates
+        .into_iter()
+        .filter(|docid| {
+            index
+                .get_document(*docid)
+                .is_some_and(|document| filter.matches(&document))
+        })
+        .collect()
+}
+
+/// The number of the value of an equality, if it's a number.
+fn equal_number(value: &str) -> Option<f64> {
+    value.parse().ok().filter(|n: &f64| n.is_finite())
+}
+
+fn value_matches(value: &Value, expected: &str) -> bool {
+    match value {
+        Value::String(s) => s.to_lowercase() == expected.to_lowercase(),
+        Value::Number(n) => n.as_f64().is_some_and(|n| expected.parse().ok() == Some(n)),
+        Value::Bool(b) => b.to_string() == expected,
+        Value::Array(values) => values.iter().any(|value| value_matches(value, expected)),
+        Value::Null | Value::Object(_) => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    And,
+    Or,
+    Not,
+    Value(String),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::OpenParen => write!(f, "("),
+            Token::CloseParen => write!(f, ")"),
+            Token::OpenBracket => write!(f, "["),
+            Token::CloseBracket => write!(f, "]"),
+            Token::Comma => write!(f, ","),
+            Token::Equal => write!(f, "="),
+            Token::NotEqual => write!(f, "!="),
+            Token::Greater => write!(f, ">"),
+            Token::GreaterOrEqual => write!(f, ">="),
+            Token::Less => write!(f, "<"),
+            Token::LessOrEqual => write!(f, "<="),
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::Not => write!(f, "NOT"),
+            Token::Value(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+fn lex(filter: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = filter.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            ',' => Token::Comma,
+            '=' => Token::Equal,
+            '!' if chars.next_if_eq(&'=').is_some() => Token::NotEqual,
+            '>' if chars.next_if_eq(&'=').is_some() => Token::GreaterOrEqual,
+            '>' => Token::Greater,
+            '<' if chars.next_if_eq(&'=').is_some() => Token::LessOrEqual,
+            '<' => Token::Less,
+            '"' | '\'' => Token::Value(quoted(&mut chars, c)?),
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|c| !c.is_whitespace() && !"()[],=!<>".contains(*c))
+                {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Value(word),
+                }
+            }
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// Read a string up to its closing quote, a quote can be escaped with a `\`.
+fn quoted(chars: &mut Peekable<Chars>, quote: char) -> Result<String, String> {
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some('\\') => value.extend(chars.next()),
+            Some(c) if c == quote => return Ok(value),
+            Some(c) => value.push(c),
+            None => return Err(format!("Missing a closing `{quote}` in the filter.")),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+    /// The number of parentheses and `NOT` around the current token.
+    depth: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.current).cloned();
+        self.current += 1;
+        token
+    }
+
+    fn next_if_eq(&mut self, expected: &Token) -> bool {
+        let found = self.tokens.get(self.current) == Some(expected);
+        if found {
+            self.current += 1;
+        }
+        found
+    }
+
+    /// Go one level deeper in the filter, if it's not nested too deeply already.
+    fn nest(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(String::from("The filter is nested too deeply."));
+        }
+        Ok(())
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.and()?;
+        while self.next_if_eq(&Token::Or) {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.not()?;
+        while self.next_if_eq(&Token::And) {
+            filter = Filter::And(Box::new(filter), Box::new(self.not()?));
+        }
+        Ok(filter)
+    }
+
+    fn not(&mut self) -> Result<Filter, String> {
+        if self.next_if_eq(&Token::Not) {
+            self.nest()?;
+            let filter = Filter::Not(Box::new(self.not()?));
+            self.depth -= 1;
+            Ok(filter)
+        } else {
+            self.condition()
+        }
+    }
+
+    fn condition(&mut self) -> Result<Filter, String> {
+        let field = match self.next() {
+            Some(Token::OpenParen) => {
+                self.nest()?;
+                let filter = self.or()?;
+                self.depth -= 1;
+                return match self.next() {
+                    Some(Token::CloseParen) => Ok(filter),
+                    _ => Err(String::from("Missing a closing `)` in the filter.")),
+                };
+            }
+            Some(Token::Value(function)) if function == "_geoRadius" => return self.geo_radius(),
+            Some(Token::Value(function)) if function == "_geoBoundingBox" => {
+                return self.geo_bounding_box()
+            }
+            Some(Token::Value(field)) => field,
+            Some(token) => return Err(format!("Expected a field but found `{token}`.")),
+            None => return Err(String::from("Expected a field at the end of the filter.")),
+        };
+        let operator = match self.next() {
+            Some(
+                token @ (Token::Equal
+                | Token::NotEqual
+                | Token::Greater
+                | Token::GreaterOrEqual
+                | Token::Less
+                | Token::LessOrEqual),
+            ) => token,
+            _ => {
+                return Err(format!(
+                    "Expected `=`, `!=`, `>`, `>=`, `<` or `<=` after `{field}` in the filter."
+                ))
+            }
+        };
+        let value = match self.next() {
+            Some(Token::Value(value)) => value,
+            _ => return Err(format!("Expected a value after `{field}` in the filter.")),
+        };
+
+        let number = || {
+            parse_number(&value).ok_or_else(|| {
+                format!(
+                    "Expected a number or a date after `{field} {operator}` but found `{value}`."
+                )
+            })
+        };
+        let range = match operator {
+            Token::Equal => return Ok(Filter::Equal { field, value }),
+            Token::NotEqual => return Ok(Filter::Not(Box::new(Filter::Equal { field, value }))),
+            Token::Greater => (Bound::Excluded(number()?), Bound::Unbounded),
+            Token::GreaterOrEqual => (Bound::Included(number()?), Bound::Unbounded),
+            Token::Less => (Bound::Unbounded, Bound::Excluded(number()?)),
+            Token::LessOrEqual => (Bound::Unbounded, Bound::Included(number()?)),
+            _ => unreachable!(),
+        };
+        Ok(Filter::Range { field, range })
+    }
+
+    /// `_geoRadius(lat, lng, meters)`
+    fn geo_radius(&mut self) -> Result<Filter, String> {
+        self.expect(Token::OpenParen, "_geoRadius")?;
+        let center = self.point("_geoRadius")?;
+        self.expect(Token::Comma, "_geoRadius")?;
+        let meters = self.number("_geoRadius")?;
+        self.expect(Token::CloseParen, "_geoRadius")?;
+        if meters < 0.0 {
+            return Err(String::from(
+                "The radius of `_geoRadius` can't be negative.",
+            ));
+        }
+        Ok(Filter::GeoRadius { center, meters })
+    }
+
+    /// `_geoBoundingBox([north, west], [south, east])`, the top left and bottom right corners.
+    fn geo_bounding_box(&mut self) -> Result<Filter, String> {
+        let function = "_geoBoundingBox";
+        self.expect(Token::OpenParen, function)?;
+        let corner = |parser: &mut Self| -> Result<Point, String> {
+            parser.expect(Token::OpenBracket, function)?;
+            let point = parser.point(function)?;
+            parser.expect(Token::CloseBracket, function)?;
+            Ok(point)
+        };
+        let top_left = corner(self)?;
+        self.expect(Token::Comma, function)?;
+        let bottom_right = corner(self)?;
+        self.expect(Token::CloseParen, function)?;
+        if top_left.lat < bottom_right.lat {
+            return Err(String::from(
+                "The first corner of `_geoBoundingBox` must be north of the second one.",
+            ));
+        }
+        Ok(Filter::GeoBoundingBox(BoundingBox {
+            north: top_left.lat,
+            south: bottom_right.lat,
+            west: top_left.lng,
+            east: bottom_right.lng,
+        }))
+    }
+
+    fn point(&mut self, function: &str) -> Result<Point, String> {
+        let lat = self.number(function)?;
+        self.expect(Token::Comma, function)?;
+        let lng = self.number(function)?;
+        let point = Point { lat, lng };
+        if point.is_valid() {
+            Ok(point)
+        } else {
+            Err(format!(
+                "Invalid coordinates `{lat}, {lng}` in `{function}`, the latitude must be between -90 and 90 and the longitude between -180 and 180."
+            ))
+        }
+    }
+
+    fn number(&mut self, function: &str) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Value(value)) => value
+                .parse()
+                .ok()
+                .filter(|number: &f64| number.is_finite())
+                .ok_or_else(|| format!("Expected a number in `{function}` but found `{value}`.")),
+            Some(token) => Err(format!(
+                "Expected a number in `{function}` but found `{token}`."
+            )),
+            None => Err(format!("Missing arguments to `{function}`.")),
+        }
+    }
+
+    fn expect(&mut self, expected: Token, function: &str) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!(
+                "Expected `{expected}` in `{function}` but found `{token}`."
+            )),
+            None => Err(format!("Missing a `{expected}` in `{function}`.")),
+        }
+    }
+}
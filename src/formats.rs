@@ -0,0 +1,217 @@
+//! The formats of the documents sent and returned by the API: JSON, NDJSON, one document per
+//! line, and CSV, one document per row. The CSV returned has the nested objects flattened in
+//! columns like `author.name`, the CSV sent can give a type to its columns like `year:number`.
+
+use std::{
+    io::{BufReader, Read},
+    iter,
+};
+
+use axum::http::HeaderValue;
+use serde_json::{Map, Number, Value};
+
+use crate::{api::OneOrMany, Document};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Ndjson,
+    Csv,
+}
+
+impl Format {
+    /// The first format of an `Accept` or `Content-Type` header we know about, JSON by default.
+    pub fn from_header(header: Option<&HeaderValue>) -> Self {
+        let header = match header.and_then(|header| header.to_str().ok()) {
+            Some(header) => header,
+            None => return Format::Json,
+        };
+        header
+            .split(',')
+            .filter_map(|media| match media.split(';').next()?.trim() {
+                "application/json" => Some(Format::Json),
+                "application/x-ndjson" | "application/ndjson" => Some(Format::Ndjson),
+                "text/csv" => Some(Format::Csv),
+                _ => None,
+            })
+            .next()
+            .unwrap_or(Format::Json)
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Ndjson => "application/x-ndjson",
+            Format::Csv => "text/csv",
+        }
+    }
+}
+
+/// The attributes of a document with the nested objects flattened,
+/// `{ "author": { "name": "Hugo" } }` becomes `{ "author.name": "Hugo" }`.
+pub fn flatten(document: &Document) -> Map<String, Value> {
+    fn flatten_into(prefix: &str, value: &Value, flattened: &mut Map<String, Value>) {
+        match value {
+            Value::Object(object) => {
+                for (field, value) in object {
+                    flatten_into(&format!("{prefix}.{field}"), value, flattened);
+                }
+            }
+            value => {
+                flattened.insert(prefix.to_string(), value.clone());
+            }
+        }
+    }
+
+    let mut flattened = Map::new();
+    for (field, value) in document.attributes() {
+        flatten_into(field, value, &mut flattened);
+    }
+    flattened
+}
+
+/// Add the columns of a document that are missing, in the order of its attributes.
+pub fn extend_columns(columns: &mut Vec<String>, document: &Document) {
+    for column in flatten(document).keys() {
+        if !columns.contains(column) {
+            columns.push(column.clone());
+        }
+    }
+}
+
+/// A chunk of the documents of a JSON array, preceded by a comma unless it starts the array.
+pub fn write_json(documents: &[Document], first: bool) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for (i, document) in documents.iter().enumerate() {
+        if i > 0 || !first {
+            buffer.push(b',');
+        }
+        serde_json::to_writer(&mut buffer, document).expect("Can't serialize a document");
+    }
+    buffer
+}
+
+/// One document per line.
+pub fn write_ndjson(documents: &[Document]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for document in documents {
+        serde_json::to_writer(&mut buffer, document).expect("Can't serialize a document");
+        buffer.push(b'\n');
+    }
+    buffer
+}
+
+/// The header row of a CSV, nothing without any column.
+pub fn write_csv_header(columns: &[String]) -> Vec<u8> {
+    if columns.is_empty() {
+        return Vec::new();
+    }
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(columns).expect("Can't write in memory");
+    writer.into_inner().expect("Can't write in memory")
+}
+
+/// One document per row, with an empty cell for each missing column. The strings are written
+/// as is, the arrays and the objects not flattened in the columns as JSON.
+pub fn write_csv(columns: &[String], documents: &[Document]) -> Vec<u8> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for document in documents {
+        let flattened = flatten(document);
+        let value = |column: &String| flattened.get(column).or_else(|| document.get(column));
+        let record = columns.iter().map(|column| match value(column) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(value) => value.to_string(),
+        });
+        writer.write_record(record).expect("Can't write in memory");
+    }
+    writer.into_inner().expect("Can't write in memory")
+}
+
+/// The documents of a body, read one by one from NDJSON or CSV. A JSON body, a document or an
+/// array of documents, is read at once. serde_json reads byte by byte, so the body is buffered.
+pub fn read_documents<R: Read + Send + 'static>(
+    format: Format,
+    reader: R,
+) -> Box<dyn Iterator<Item = Result<Document, String>> + Send> {
+    match format {
+        Format::Json => match serde_json::from_reader(BufReader::new(reader)) {
+            Ok(OneOrMany::One(document)) => Box::new(iter::once(Ok(document))),
+            Ok(OneOrMany::Multiple(documents)) => Box::new(documents.into_iter().map(Ok)),
+            Err(e) => Box::new(iter::once(Err(format!("Invalid JSON: {e}")))),
+        },
+        Format::Ndjson => Box::new(
+            serde_json::Deserializer::from_reader(BufReader::new(reader))
+                .into_iter()
+                .map(|document| document.map_err(|e| format!("Invalid NDJSON: {e}"))),
+        ),
+        Format::Csv => read_csv(reader),
+    }
+}
+
+/// The type of the values of a column of a CSV, a string unless the header says otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    String,
+    Number,
+    Boolean,
+}
+
+/// Parse a header like `year:number`, `title:string` or `title`.
+fn parse_header(header: &str) -> (String, ColumnType) {
+    match header.rsplit_once(':') {
+        Some((name, "string")) => (name.to_string(), ColumnType::String),
+        Some((name, "number")) => (name.to_string(), ColumnType::Number),
+        Some((name, "boolean")) => (name.to_string(), ColumnType::Boolean),
+        _ => (header.to_string(), ColumnType::String),
+    }
+}
+
+/// The value of a cell of a CSV, the empty cells of the numbers and the booleans are `null`.
+fn parse_cell(cell: &str, column_type: ColumnType) -> Result<Value, String> {
+    let trimmed = cell.trim();
+    match column_type {
+        ColumnType::String => Ok(Value::String(cell.to_string())),
+        ColumnType::Number | ColumnType::Boolean if trimmed.is_empty() => Ok(Value::Null),
+        ColumnType::Number => match trimmed.parse::<i64>() {
+            Ok(number) => Ok(Value::from(number)),
+            Err(_) => trimmed
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| format!("`{cell}` is not a number")),
+        },
+        ColumnType::Boolean => match trimmed {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => Err(format!("`{cell}` is not a boolean")),
+        },
+    }
+}
+
+/// The documents of a CSV, one per row after the header.
+fn read_csv<R: Read + Send + 'static>(
+    reader: R,
+) -> Box<dyn Iterator<Item = Result<Document, String>> + Send> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let columns: Vec<_> = match reader.headers() {
+        Ok(headers) => headers.iter().map(parse_header).collect(),
+        Err(e) => return Box::new(iter::once(Err(format!("Invalid CSV: {e}")))),
+    };
+    Box::new(reader.into_records().map(move |record| {
+        let record = record.map_err(|e| format!("Invalid CSV: {e}"))?;
+        let line = record.position().map_or(0, |position| position.line());
+        columns
+            .iter()
+            .zip(record.iter())
+            .map(|((name, column_type), cell)| {
+                let value = parse_cell(cell, *column_type).map_err(|e| {
+                    format!("Invalid CSV: {e} in the column `{name}` on line {line}")
+                })?;
+                Ok((name.clone(), value))
+            })
+            .collect::<Result<Map<_, _>, String>>()
+            .map(Document::from)
+    }))
+}
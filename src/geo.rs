@@ -0,0 +1,306 @@
+//! The documents with a `_geo` attribute, like `{ "lat": 48.85, "lng": 2.35 }`, are indexed
+//! in the geohash cells containing them, from the largest cells to cells of about 150 meters.
+
+use std::fmt;
+
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Index, Order};
+
+/// The attribute containing the position of the documents.
+pub const GEO_FIELD: &str = "_geo";
+/// The coordinates of the documents are indexed as numbers under these attributes.
+pub const GEO_LAT_FIELD: &str = "_geo.lat";
+pub const GEO_LNG_FIELD: &str = "_geo.lng";
+/// The number of chars of the smallest geohash cells a document is indexed in.
+const PRECISION: usize = 7;
+/// The maximum number of cells fetched to cover an area, the larger cells are used past it.
+const MAX_CELLS: usize = 32;
+const EARTH_RADIUS: f64 = 6_371_000.0;
+const BASE32: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl Point {
+    /// Read a `{ "lat": .., "lng": .. }` object, the coordinates can also be strings.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let coordinate = |name: &str| match value.get(name)? {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        };
+        let point = Point {
+            lat: coordinate("lat")?,
+            lng: coordinate("lng")?,
+        };
+        point.is_valid().then_some(point)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        (-90.0..=90.0).contains(&self.lat) && (-180.0..=180.0).contains(&self.lng)
+    }
+
+    /// The distance to another point in meters, with the haversine formula.
+    pub fn distance(&self, other: &Point) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlng = (other.lng - self.lng).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlng / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().asin()
+    }
+
+    /// The distances to the closest and the farthest points of a box of latitudes and longitudes.
+    /// The farthest point is looked for among the corners, which is only exact for small boxes.
+    pub fn distance_bounds(&self, lat: (f64, f64), lng: (f64, f64)) -> (f64, f64) {
+        let closest = Point {
+            lat: self.lat.clamp(lat.0, lat.1),
+            lng: self.lng.clamp(lng.0, lng.1),
+        };
+        let farthest = [
+            (lat.0, lng.0),
+            (lat.0, lng.1),
+            (lat.1, lng.0),
+            (lat.1, lng.1),
+        ]
+        .into_iter()
+        .map(|(lat, lng)| self.distance(&Point { lat, lng }))
+        .fold(0.0, f64::max);
+        (self.distance(&closest), farthest)
+    }
+
+    /// The geohash cells containing the point, from the largest to the smallest.
+    pub fn cells(&self) -> Vec<String> {
+        let geohash = encode(self, PRECISION);
+        (1..=PRECISION)
+            .map(|len| geohash[..len].to_string())
+            .collect()
+    }
+}
+
+/// Sort the documents by their distance to a point, like `_geoPoint(48.85, 2.35):asc`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoSort {
+    pub point: Point,
+    pub order: Order,
+}
+
+impl GeoSort {
+    pub fn parse(sort: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!("Invalid sort `{sort}`, expected `_geoPoint(lat, lng):asc` or `_geoPoint(lat, lng):desc`")
+        };
+        let (point, order) = match sort.trim().rsplit_once(':') {
+            Some((point, "asc")) => (point, Order::Asc),
+            Some((point, "desc")) => (point, Order::Desc),
+            _ => return Err(invalid()),
+        };
+        let (lat, lng) = point
+            .trim()
+            .strip_prefix("_geoPoint(")
+            .and_then(|point| point.strip_suffix(')'))
+            .and_then(|point| point.split_once(','))
+            .ok_or_else(invalid)?;
+        let point = Point {
+            lat: lat.trim().parse().map_err(|_| invalid())?,
+            lng: lng.trim().parse().map_err(|_| invalid())?,
+        };
+        if !point.is_valid() {
+            return Err(format!(
+                "Invalid coordinates in `{sort}`, the latitude must be between -90 and 90 and the longitude between -180 and 180."
+            ));
+        }
+        Ok(GeoSort { point, order })
+    }
+}
+
+impl fmt::Display for GeoSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Point { lat, lng } = self.point;
+        match self.order {
+            Order::Asc => write!(f, "_geoPoint({lat}, {lng}):asc"),
+            Order::Desc => write!(f, "_geoPoint({lat}, {lng}):desc"),
+        }
+    }
+}
+
+/// An area between two latitudes and two longitudes, the west can be greater than
+/// the east when the box crosses the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub north: f64,
+    pub south: f64,
+    pub west: f64,
+    pub east: f64,
+}
+
+impl BoundingBox {
+    /// The smallest box containing a circle.
+    pub fn around(center: &Point, meters: f64) -> Self {
+        let angle = meters / EARTH_RADIUS;
+        let north = center.lat + angle.to_degrees();
+        let south = center.lat - angle.to_degrees();
+        if north >= 90.0 || south <= -90.0 {
+            // the circle contains a pole
+            return BoundingBox {
+                north: north.min(90.0),
+                south: south.max(-90.0),
+                west: -180.0,
+                east: 180.0,
+            };
+        }
+
+        let delta = (angle.sin() / center.lat.to_radians().cos())
+            .asin()
+            .to_degrees();
+        if delta.is_nan() || delta >= 180.0 {
+            return BoundingBox {
+                north,
+                south,
+                west: -180.0,
+                east: 180.0,
+            };
+        }
+        let wrap = |lng: f64| match lng {
+            lng if lng < -180.0 => lng + 360.0,
+            lng if lng > 180.0 => lng - 360.0,
+            lng => lng,
+        };
+        BoundingBox {
+            north,
+            south,
+            west: wrap(center.lng - delta),
+            east: wrap(center.lng + delta),
+        }
+    }
+
+    pub fn contains(&self, point: &Point) -> bool {
+        let lng = if self.west <= self.east {
+            (self.west..=self.east).contains(&point.lng)
+        } else {
+            point.lng >= self.west || point.lng <= self.east
+        };
+        (self.south..=self.north).contains(&point.lat) && lng
+    }
+
+    /// The ranges of longitudes of the box, two when it crosses the antimeridian.
+    fn longitudes(&self) -> Vec<(f64, f64)> {
+        if self.west <= self.east {
+            vec![(self.west, self.east)]
+        } else {
+            vec![(self.west, 180.0), (-180.0, self.east)]
+        }
+    }
+
+    /// The smallest geohash cells covering the box without going over `MAX_CELLS`.
+    fn cells(&self) -> Vec<String> {
+        for precision in (1..=PRECISION).rev() {
+            let bits = precision * 5;
+            let (lat_cells, lng_cells) = (1 << (bits / 2), 1 << (bits - bits / 2));
+            let height = 180.0 / lat_cells as f64;
+            let width = 360.0 / lng_cells as f64;
+            let index = |value: f64, size: f64, offset: f64, cells: usize| {
+                (((value + offset) / size).floor() as usize).min(cells - 1)
+            };
+
+            let lats = index(self.south, height, 90.0, lat_cells)
+                ..=index(self.north, height, 90.0, lat_cells);
+            let lngs: Vec<_> = self
+                .longitudes()
+                .into_iter()
+                .flat_map(|(west, east)| {
+                    index(west, width, 180.0, lng_cells)..=index(east, width, 180.0, lng_cells)
+                })
+                .collect();
+            if lats.clone().count() * lngs.len() > MAX_CELLS && precision > 1 {
+                continue;
+            }
+
+            return lats
+                .flat_map(|lat| {
+                    lngs.iter().map(move |lng| {
+                        // the center of the cell is only contained by this cell
+                        let center = Point {
+                            lat: -90.0 + (lat as f64 + 0.5) * height,
+                            lng: -180.0 + (*lng as f64 + 0.5) * width,
+                        };
+                        encode(&center, precision)
+                    })
+                })
+                .collect();
+        }
+        unreachable!("there are only 32 cells of one char")
+    }
+}
+
+/// The documents of the index in a box, according to their `_geo` attribute.
+pub fn docids_in_box<I: Index>(index: &I, area: &BoundingBox) -> RoaringBitmap {
+    let candidates = area
+        .cells()
+        .iter()
+        .map(|cell| index.facet_docids(GEO_FIELD, cell))
+        .fold(RoaringBitmap::new(), |acc, docids| acc | docids);
+    // the cells overflow the box, the exact positions must be checked
+    let data = index.ranking_data_batch(&candidates);
+    candidates
+        .into_iter()
+        .zip(data)
+        .filter(|(_, data)| data.geo.is_some_and(|point| area.contains(&point)))
+        .map(|(docid, _)| docid)
+        .collect()
+}
+
+/// The documents of the index less than `meters` away from a point.
+pub fn docids_around<I: Index>(index: &I, center: &Point, meters: f64) -> RoaringBitmap {
+    let area = BoundingBox::around(center, meters);
+    let candidates = area
+        .cells()
+        .iter()
+        .map(|cell| index.facet_docids(GEO_FIELD, cell))
+        .fold(RoaringBitmap::new(), |acc, docids| acc | docids);
+    let data = index.ranking_data_batch(&candidates);
+    candidates
+        .into_iter()
+        .zip(data)
+        .filter(|(_, data)| {
+            data.geo
+                .is_some_and(|point| point.distance(center) <= meters)
+        })
+        .map(|(docid, _)| docid)
+        .collect()
+}
+
+/// The geohash of a point, with `precision` chars.
+fn encode(point: &Point, precision: usize) -> String {
+    let (mut lat, mut lng) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut geohash = String::with_capacity(precision);
+    let mut even = true;
+
+    for _ in 0..precision {
+        let mut index = 0;
+        for _ in 0..5 {
+            let (range, value) = if even {
+                (&mut lng, point.lng)
+            } else {
+                (&mut lat, point.lat)
+            };
+            let middle = (range.0 + range.1) / 2.0;
+            index <<= 1;
+            if value >= middle {
+                index |= 1;
+                range.0 = middle;
+            } else {
+                range.1 = middle;
+            }
+            even = !even;
+        }
+        geohash.push(BASE32[index] as char);
+    }
+
+    geohash
+}
@@ -2,17 +2,25 @@ use std::{
     borrow::Cow,
     collections::{hash_map::Entry, HashMap},
     io::ErrorKind,
+    ops::Bound,
+    path::Path,
+    sync::Arc,
 };
 
 use heed::{
-    types::{OwnedType, SerdeJson, Str},
-    Database, Env, RwTxn,
+    types::{DecodeIgnore, OwnedType, SerdeJson, Str},
+    Database, Env, PolyDatabase, RwTxn, BEU32,
 };
 use roaring::RoaringBitmap;
 
-use crate::{tokenize, DocId, Document, Query};
+use crate::{
+    numbers::{key_number, key_range, NumberRange},
+    DocId, Document, Rules, Settings, Tokenizer,
+};
 
-use super::Index;
+use super::{
+    facet_key, index_path, index_uids, DirtyPostings, Extracted, Extractor, Index, RankingData,
+};
 
 const DB_NAME: &str = "heed.db";
 
@@ -20,12 +28,33 @@ const DB_NAME: &str = "heed.db";
 mod db_name {
     pub const DOCUMENTS: &str = "documents";
     pub const WORDS: &str = "words";
+    pub const STEMS: &str = "stems";
+    pub const FACETS: &str = "facets";
+    pub const NUMBERS: &str = "numbers";
+    pub const RANKING: &str = "ranking";
+    pub const VECTORS: &str = "vectors";
+    pub const MAIN: &str = "main";
+}
+
+/// Keys of the entries stored in the main database.
+mod main_key {
+    pub const SETTINGS: &str = "settings";
+    pub const RULES: &str = "rules";
+    pub const VERSION: &str = "version";
 }
 
 pub struct Heed {
     env: Env,
-    documents: Database<OwnedType<u32>, SerdeJson<Document>>,
+    /// Keyed by the big-endian ids to be sorted by id.
+    documents: Database<OwnedType<BEU32>, SerdeJson<Document>>,
     words: Database<Str, SerdeJson<RoaringBitmap>>,
+    stems: Database<Str, SerdeJson<RoaringBitmap>>,
+    facets: Database<Str, SerdeJson<RoaringBitmap>>,
+    numbers: Database<Str, SerdeJson<RoaringBitmap>>,
+    ranking: Database<OwnedType<DocId>, SerdeJson<RankingData>>,
+    vectors: Database<OwnedType<DocId>, SerdeJson<Vec<f32>>>,
+    main: PolyDatabase,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
 }
 
 impl Heed {
@@ -33,98 +62,145 @@ impl Heed {
         &self,
         wtxn: &mut RwTxn,
         document: Document,
-        dirty_words: &mut HashMap<String, RoaringBitmap>,
+        extractor: &Extractor,
+        dirty: &mut DirtyPostings,
     ) {
         let docid = document.docid();
 
         // first we delete the old version of the document
-        self.delete_document(wtxn, docid, dirty_words);
+        self.delete_document(wtxn, docid, extractor, dirty);
 
-        let mut words: Vec<_> = document.fields().flat_map(tokenize).collect();
-        // if a word is present multiple times in the same field we only count it once
-        words.sort_unstable();
-        words.dedup();
-
-        for word in words.into_iter().filter(|w| !w.is_empty()) {
-            match dirty_words.entry(word) {
-                Entry::Occupied(mut bitmap) => {
-                    bitmap.get_mut().insert(docid);
-                }
-                Entry::Vacant(entry) => {
-                    // We get the current value from the db
-                    let mut bitmap = self
-                        .words
-                        .get(&wtxn, &entry.key())
-                        .unwrap()
-                        .unwrap_or_default();
-                    bitmap.insert(docid);
-                    entry.insert(bitmap);
-                }
-            };
+        let Extracted {
+            words,
+            stems,
+            facets,
+            numbers,
+            ranking,
+            vector,
+        } = extractor.extract(&document);
+        for word in words {
+            Self::dirty_bitmap(wtxn, self.words, &mut dirty.words, word).insert(docid);
+        }
+        for stem in stems {
+            Self::dirty_bitmap(wtxn, self.stems, &mut dirty.stems, stem).insert(docid);
+        }
+        for facet in facets {
+            Self::dirty_bitmap(wtxn, self.facets, &mut dirty.facets, facet).insert(docid);
+        }
+        for number in numbers {
+            Self::dirty_bitmap(wtxn, self.numbers, &mut dirty.numbers, number).insert(docid);
         }
 
-        self.documents.put(wtxn, &docid, &document).unwrap();
+        self.ranking.put(wtxn, &docid, &ranking).unwrap();
+        if let Some(vector) = vector {
+            self.vectors.put(wtxn, &docid, &vector).unwrap();
+        }
+        self.documents
+            .put(wtxn, &BEU32::new(docid), &document)
+            .unwrap();
     }
 
     fn delete_document(
         &self,
         wtxn: &mut RwTxn,
         docid: DocId,
-        dirty_words: &mut HashMap<String, RoaringBitmap>,
+        extractor: &Extractor,
+        dirty: &mut DirtyPostings,
     ) {
-        if let Some(document) = self.documents.get(wtxn, &docid).unwrap() {
-            self.documents.delete(wtxn, &docid).unwrap();
+        if let Some(document) = self.documents.get(wtxn, &BEU32::new(docid)).unwrap() {
+            self.documents.delete(wtxn, &BEU32::new(docid)).unwrap();
+            self.ranking.delete(wtxn, &docid).unwrap();
+            self.vectors.delete(wtxn, &docid).unwrap();
             // we get all the words in a document and then get rids of our id for each of these words
-            let mut words: Vec<_> = document.fields().flat_map(tokenize).collect();
-            // if a word is present multiple times in the same field we only count it once
-            words.sort_unstable();
-            words.dedup();
-
-            for word in words.into_iter().filter(|w| !w.is_empty()) {
-                match dirty_words.entry(word) {
-                    Entry::Occupied(mut bitmap) => {
-                        bitmap.get_mut().insert(docid);
-                    }
-                    Entry::Vacant(entry) => {
-                        // We get the current value from the db
-                        let mut bitmap = self.words.get(&wtxn, entry.key()).unwrap().unwrap();
-                        bitmap.remove(docid);
-                        entry.insert(bitmap);
-                    }
-                }
+            let Extracted {
+                words,
+                stems,
+                facets,
+                numbers,
+                ..
+            } = extractor.extract(&document);
+            for word in words {
+                Self::dirty_bitmap(wtxn, self.words, &mut dirty.words, word).remove(docid);
+            }
+            for stem in stems {
+                Self::dirty_bitmap(wtxn, self.stems, &mut dirty.stems, stem).remove(docid);
+            }
+            for facet in facets {
+                Self::dirty_bitmap(wtxn, self.facets, &mut dirty.facets, facet).remove(docid);
+            }
+            for number in numbers {
+                Self::dirty_bitmap(wtxn, self.numbers, &mut dirty.numbers, number).remove(docid);
             }
         }
     }
 
-    /// Update all the entry in the dirty words.
-    fn apply_dirty_words(
-        &self,
-        wtxn: &mut RwTxn,
-        dirty_words: &mut HashMap<String, RoaringBitmap>,
-    ) {
-        for (word, bitmap) in dirty_words.into_iter() {
-            self.words.put(wtxn, word, bitmap).unwrap();
+    /// Get the bitmap of a key from the dirty postings,
+    /// the first time a key is modified its bitmap is fetched from the database.
+    fn dirty_bitmap<'a>(
+        wtxn: &RwTxn,
+        database: Database<Str, SerdeJson<RoaringBitmap>>,
+        dirty: &'a mut HashMap<String, RoaringBitmap>,
+        key: String,
+    ) -> &'a mut RoaringBitmap {
+        match dirty.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                // We get the current value from the db
+                let bitmap = database.get(wtxn, entry.key()).unwrap().unwrap_or_default();
+                entry.insert(bitmap)
+            }
+        }
+    }
+
+    /// Update all the entries in the dirty postings.
+    fn apply_dirty_postings(&self, wtxn: &mut RwTxn, dirty: DirtyPostings) {
+        for (database, postings) in [
+            (self.words, dirty.words),
+            (self.stems, dirty.stems),
+            (self.facets, dirty.facets),
+            (self.numbers, dirty.numbers),
+        ] {
+            for (key, bitmap) in postings {
+                if bitmap.is_empty() {
+                    database.delete(wtxn, &key).unwrap();
+                } else {
+                    database.put(wtxn, &key, &bitmap).unwrap();
+                }
+            }
         }
     }
 }
 
 impl Default for Heed {
     fn default() -> Self {
-        match std::fs::create_dir(DB_NAME) {
+        Self::open_path(Path::new(DB_NAME))
+    }
+}
+
+impl Heed {
+    fn open_path(path: &Path) -> Self {
+        match std::fs::create_dir(path) {
             Ok(()) => (),
             Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => (),
             e => e.unwrap(),
         }
 
         let mut options = heed::EnvOpenOptions::new();
-        options.max_dbs(3);
+        options.max_dbs(8);
         options.map_size(1024 * 1024 * 1024);
-        let env = options.open(&DB_NAME).unwrap();
+        let env = options.open(path).unwrap();
 
         Self {
             documents: env.create_database(Some(db_name::DOCUMENTS)).unwrap(),
             words: env.create_database(Some(db_name::WORDS)).unwrap(),
+            stems: env.create_database(Some(db_name::STEMS)).unwrap(),
+            facets: env.create_database(Some(db_name::FACETS)).unwrap(),
+            numbers: env.create_database(Some(db_name::NUMBERS)).unwrap(),
+            ranking: env.create_database(Some(db_name::RANKING)).unwrap(),
+            vectors: env.create_database(Some(db_name::VECTORS)).unwrap(),
+            main: env.create_poly_database(Some(db_name::MAIN)).unwrap(),
             env,
+            tokenizer: None,
         }
     }
 }
@@ -141,42 +217,238 @@ impl Index for Heed {
 
     fn get_document(&self, id: DocId) -> Option<Cow<Document>> {
         let rtxn = self.env.read_txn().unwrap();
-        self.documents.get(&rtxn, &id).unwrap().map(Cow::Owned)
+        self.documents
+            .get(&rtxn, &BEU32::new(id))
+            .unwrap()
+            .map(Cow::Owned)
+    }
+
+    fn documents_after(&self, after: Option<DocId>, limit: usize) -> Vec<Cow<Document>> {
+        let rtxn = self.env.read_txn().unwrap();
+        let start = after.map_or(Bound::Unbounded, |after| Bound::Excluded(BEU32::new(after)));
+        self.documents
+            .range(&rtxn, &(start, Bound::Unbounded))
+            .unwrap()
+            .take(limit)
+            .map(|entry| Cow::Owned(entry.unwrap().1))
+            .collect()
+    }
+
+    fn nb_documents(&self) -> u64 {
+        let rtxn = self.env.read_txn().unwrap();
+        self.documents.len(&rtxn).unwrap()
     }
 
     /// Since deser+ser the bitmaps for each insertion is slow we're going to create a
     /// temporary map of the words databases containing only the modified words.
     /// Then we reinsert + serialize all the modified words at once.
     fn add_documents(&mut self, documents: Vec<Document>) {
+        let extractor = Extractor::new(self);
         let mut wtxn = self.env.write_txn().unwrap();
-        let mut dirty_words = HashMap::new();
+        let mut dirty = DirtyPostings::default();
 
         documents
             .into_iter()
-            .for_each(|document| self.add_document(&mut wtxn, document, &mut dirty_words));
+            .for_each(|document| self.add_document(&mut wtxn, document, &extractor, &mut dirty));
 
-        self.apply_dirty_words(&mut wtxn, &mut dirty_words);
+        self.apply_dirty_postings(&mut wtxn, dirty);
         wtxn.commit().unwrap();
     }
 
     fn delete_documents(&mut self, docids: Vec<DocId>) {
+        let extractor = Extractor::new(self);
         let mut wtxn = self.env.write_txn().unwrap();
-        let mut dirty_words = HashMap::new();
+        let mut dirty = DirtyPostings::default();
 
         for docid in docids {
-            self.delete_document(&mut wtxn, docid, &mut dirty_words);
+            self.delete_document(&mut wtxn, docid, &extractor, &mut dirty);
         }
-        self.apply_dirty_words(&mut wtxn, &mut dirty_words);
+        self.apply_dirty_postings(&mut wtxn, dirty);
         wtxn.commit().unwrap();
     }
 
-    fn search(&self, query: &Query) -> Vec<DocId> {
+    fn word_docids(&self, word: &str) -> RoaringBitmap {
+        let rtxn = self.env.read_txn().unwrap();
+        self.words.get(&rtxn, word).unwrap().unwrap_or_default()
+    }
+
+    fn contains_word(&self, word: &str) -> bool {
+        let rtxn = self.env.read_txn().unwrap();
+        // the empty bitmaps are deleted, we don't need to decode it
+        self.words
+            .remap_data_type::<DecodeIgnore>()
+            .get(&rtxn, word)
+            .unwrap()
+            .is_some()
+    }
+
+    fn words_starting_with(&self, prefix: &str) -> Vec<String> {
+        let rtxn = self.env.read_txn().unwrap();
+        self.words
+            .remap_data_type::<DecodeIgnore>()
+            .prefix_iter(&rtxn, prefix)
+            .unwrap()
+            .map(|entry| entry.unwrap().0.to_string())
+            .collect()
+    }
+
+    fn word_frequencies(&self) -> Vec<(String, u64)> {
+        let rtxn = self.env.read_txn().unwrap();
+        self.words
+            .iter(&rtxn)
+            .unwrap()
+            .map(|entry| {
+                let (word, docids) = entry.unwrap();
+                (word.to_string(), docids.len())
+            })
+            .collect()
+    }
+
+    fn stem_docids(&self, stem: &str) -> RoaringBitmap {
+        let rtxn = self.env.read_txn().unwrap();
+        self.stems.get(&rtxn, stem).unwrap().unwrap_or_default()
+    }
+
+    fn facet_docids(&self, field: &str, value: &str) -> RoaringBitmap {
         let rtxn = self.env.read_txn().unwrap();
-        let docids = tokenize(query.q.as_deref().unwrap_or(""))
-            .filter_map(|word| self.words.get(&rtxn, &word).unwrap())
-            .fold(RoaringBitmap::default(), |acc, bitmap| acc | bitmap);
+        self.facets
+            .get(&rtxn, &facet_key(field, value))
+            .unwrap()
+            .unwrap_or_default()
+    }
+
+    fn number_postings(&self, field: &str, range: NumberRange) -> Vec<(f64, RoaringBitmap)> {
+        let rtxn = self.env.read_txn().unwrap();
+        let (start, end) = key_range(field, range);
+        let range: (Bound<&str>, Bound<&str>) = (
+            start.as_ref().map(String::as_str),
+            end.as_ref().map(String::as_str),
+        );
+        self.numbers
+            .range(&rtxn, &range)
+            .unwrap()
+            .map(|entry| {
+                let (key, docids) = entry.unwrap();
+                (key_number(key), docids)
+            })
+            .collect()
+    }
+
+    fn number_bounds(&self, field: &str) -> Option<(f64, f64)> {
+        let rtxn = self.env.read_txn().unwrap();
+        let (start, end) = key_range(field, (Bound::Unbounded, Bound::Unbounded));
+        let range: (Bound<&str>, Bound<&str>) = (
+            start.as_ref().map(String::as_str),
+            end.as_ref().map(String::as_str),
+        );
+        // the empty bitmaps are deleted, we don't need to decode them
+        let numbers = self.numbers.remap_data_type::<DecodeIgnore>();
+        let (min, ()) = numbers.range(&rtxn, &range).unwrap().next()?.unwrap();
+        let (max, ()) = numbers.rev_range(&rtxn, &range).unwrap().next()?.unwrap();
+        Some((key_number(min), key_number(max)))
+    }
+
+    fn ranking_data(&self, docid: DocId) -> RankingData {
+        let rtxn = self.env.read_txn().unwrap();
+        self.ranking.get(&rtxn, &docid).unwrap().unwrap_or_default()
+    }
+
+    fn ranking_data_batch(&self, docids: &RoaringBitmap) -> Vec<RankingData> {
+        let rtxn = self.env.read_txn().unwrap();
+        docids
+            .iter()
+            .map(|docid| self.ranking.get(&rtxn, &docid).unwrap().unwrap_or_default())
+            .collect()
+    }
+
+    fn vectors(&self) -> Vec<(DocId, Vec<f32>)> {
+        let rtxn = self.env.read_txn().unwrap();
+        self.vectors
+            .iter(&rtxn)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .collect()
+    }
+
+    fn settings(&self) -> Settings {
+        let rtxn = self.env.read_txn().unwrap();
+        self.main
+            .get::<_, Str, SerdeJson<Settings>>(&rtxn, main_key::SETTINGS)
+            .unwrap()
+            .unwrap_or_default()
+    }
+
+    fn put_settings(&mut self, settings: Settings) {
+        let mut wtxn = self.env.write_txn().unwrap();
+        self.main
+            .put::<_, Str, SerdeJson<Settings>>(&mut wtxn, main_key::SETTINGS, &settings)
+            .unwrap();
+        wtxn.commit().unwrap();
+    }
+
+    fn curation_rules(&self) -> Rules {
+        let rtxn = self.env.read_txn().unwrap();
+        self.main
+            .get::<_, Str, SerdeJson<Rules>>(&rtxn, main_key::RULES)
+            .unwrap()
+            .unwrap_or_default()
+    }
+
+    fn put_curation_rules(&mut self, rules: Rules) {
+        let mut wtxn = self.env.write_txn().unwrap();
+        self.main
+            .put::<_, Str, SerdeJson<Rules>>(&mut wtxn, main_key::RULES, &rules)
+            .unwrap();
+        wtxn.commit().unwrap();
+    }
+
+    fn custom_tokenizer(&self) -> Option<Arc<dyn Tokenizer>> {
+        self.tokenizer.clone()
+    }
+
+    fn set_tokenizer(&mut self, tokenizer: Arc<dyn Tokenizer>) {
+        self.tokenizer = Some(tokenizer);
+    }
+
+    fn version(&self) -> u32 {
+        let rtxn = self.env.read_txn().unwrap();
+        self.main
+            .get::<_, Str, OwnedType<u32>>(&rtxn, main_key::VERSION)
+            .unwrap()
+            .unwrap_or_default()
+    }
+
+    fn put_version(&mut self, version: u32) {
+        let mut wtxn = self.env.write_txn().unwrap();
+        self.main
+            .put::<_, Str, OwnedType<u32>>(&mut wtxn, main_key::VERSION, &version)
+            .unwrap();
+        wtxn.commit().unwrap();
+    }
+
+    fn clear_postings(&mut self) {
+        let mut wtxn = self.env.write_txn().unwrap();
+        self.words.clear(&mut wtxn).unwrap();
+        self.stems.clear(&mut wtxn).unwrap();
+        self.facets.clear(&mut wtxn).unwrap();
+        self.numbers.clear(&mut wtxn).unwrap();
+        self.ranking.clear(&mut wtxn).unwrap();
+        self.vectors.clear(&mut wtxn).unwrap();
+        wtxn.commit().unwrap();
+    }
+
+    fn clear_documents(&mut self) {
+        let mut wtxn = self.env.write_txn().unwrap();
+        self.documents.clear(&mut wtxn).unwrap();
+        wtxn.commit().unwrap();
+    }
+
+    fn open(uid: &str) -> Self {
+        Self::open_path(&index_path(uid, DB_NAME))
+    }
 
-        docids.into_iter().collect()
+    fn uids() -> Vec<String> {
+        index_uids(DB_NAME)
     }
 
     fn clear_database() {
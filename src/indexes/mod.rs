@@ -4,7 +4,18 @@ mod roaring;
 mod sled;
 mod sqlite;
 
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::ErrorKind,
+    iter,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use ::roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 pub use self::heed::Heed;
 pub use self::roaring::Roaring;
@@ -12,7 +23,16 @@ pub use self::sled::Sled;
 pub use naive::Naive;
 pub use sqlite::SQLite;
 
-use crate::{DocId, Document, Query};
+use crate::{
+    geo::{Point, GEO_FIELD, GEO_LAT_FIELD, GEO_LNG_FIELD},
+    numbers::{number_key, number_values, NumberRange},
+    vector::{parse_vector, VECTORS_FIELD},
+    DefaultTokenizer, DocId, Document, Hit, Query, Rules, SearchError, Settings, Token, Tokenizer,
+};
+
+/// Must be bumped every time the tokenizer produces different words for the same text,
+/// or the documents are indexed under new postings or new keys.
+pub const INDEXING_VERSION: u32 = 10;
 
 pub trait Index: Send + Sync + Default {
     /// Get all the documents in the index
@@ -21,15 +41,381 @@ pub trait Index: Send + Sync + Default {
     /// Get one document in the index
     fn get_document(&self, id: DocId) -> Option<Cow<Document>>;
 
+    /// Get at most `limit` documents following the document `after`, or from the first document.
+    /// The documents are sorted by id, so all the documents can be read chunk by chunk without
+    /// loading them all.
+    fn documents_after(&self, after: Option<DocId>, limit: usize) -> Vec<Cow<Document>>;
+
+    /// Iterate over all the documents in the index, they are read `chunk_size` at a time.
+    fn documents(&self, chunk_size: usize) -> Documents<Self> {
+        Documents {
+            index: self,
+            chunk_size,
+            after: None,
+            chunk: Vec::new().into_iter(),
+        }
+    }
+
+    /// Get the number of documents in the index
+    fn nb_documents(&self) -> u64;
+
     /// Add a batch of documents
     fn add_documents(&mut self, document: Vec<Document>);
 
     /// Add a batch of documents
     fn delete_documents(&mut self, documents: Vec<DocId>);
 
+    /// Get the documents containing a word
+    fn word_docids(&self, word: &str) -> RoaringBitmap;
+
+    /// Returns `true` if at least one document contains this word
+    fn contains_word(&self, word: &str) -> bool;
+
+    /// Get the words of the index starting with this prefix, sorted
+    fn words_starting_with(&self, prefix: &str) -> Vec<String>;
+
+    /// Get all the words of the index along with the number of documents containing them,
+    /// sorted by word
+    fn word_frequencies(&self) -> Vec<(String, u64)>;
+
+    /// Get the documents containing a word sharing this stem
+    fn stem_docids(&self, stem: &str) -> RoaringBitmap;
+
+    /// Get the documents with this value for a faceted attribute, see `facet_key`
+    fn facet_docids(&self, field: &str, value: &str) -> RoaringBitmap;
+
+    /// Get the numbers of a faceted attribute in a range along with the documents having them,
+    /// sorted by number. The dates are numbers too, see `number_key`
+    fn number_postings(&self, field: &str, range: NumberRange) -> Vec<(f64, RoaringBitmap)>;
+
+    /// Get the documents with a number of a faceted attribute in a range
+    fn number_docids(&self, field: &str, range: NumberRange) -> RoaringBitmap {
+        self.number_postings(field, range)
+            .into_iter()
+            .fold(RoaringBitmap::new(), |acc, (_, docids)| acc | docids)
+    }
+
+    /// Get the lowest and highest numbers of a faceted or ranked attribute,
+    /// `None` if no document has a number for it
+    fn number_bounds(&self, field: &str) -> Option<(f64, f64)>;
+
+    /// Get what's needed to apply the attribute boosts and the custom ranking to a document
+    fn ranking_data(&self, docid: DocId) -> RankingData;
+
+    /// Get the ranking data of several documents at once, in the order of their ids
+    fn ranking_data_batch(&self, docids: &RoaringBitmap) -> Vec<RankingData> {
+        docids
+            .iter()
+            .map(|docid| self.ranking_data(docid))
+            .collect()
+    }
+
+    /// Get the `_vectors` of all the documents having one
+    fn vectors(&self) -> Vec<(DocId, Vec<f32>)>;
+
+    /// Get the settings of the index
+    fn settings(&self) -> Settings;
+
+    /// Replace the settings of the index without touching the documents
+    fn put_settings(&mut self, settings: Settings);
+
+    /// Replace the settings of the index and reindex the documents if needed
+    fn update_settings(&mut self, settings: Settings) {
+        let reindex = self.settings().requires_reindexing(&settings);
+        self.put_settings(settings);
+        if reindex {
+            self.reindex();
+        }
+    }
+
+    /// Get the curation rules of the index
+    fn curation_rules(&self) -> Rules;
+
+    /// Replace the curation rules of the index
+    fn put_curation_rules(&mut self, rules: Rules);
+
+    /// Get the custom tokenizer of the index, if one was set
+    fn custom_tokenizer(&self) -> Option<Arc<dyn Tokenizer>>;
+
+    /// Index the documents and parse the queries with a custom tokenizer instead of the default one.
+    /// The documents already in the index are not reindexed, call `reindex` to do so.
+    fn set_tokenizer(&mut self, tokenizer: Arc<dyn Tokenizer>);
+
+    /// Get the tokenizer used to index the documents and parse the queries
+    fn tokenizer(&self, settings: &Settings) -> Arc<dyn Tokenizer> {
+        self.custom_tokenizer()
+            .unwrap_or_else(|| Arc::new(DefaultTokenizer::new(settings)))
+    }
+
+    /// Get the version of the tokenizer used to index the documents,
+    /// `0` if the index was created before the versions were stored
+    fn version(&self) -> u32;
+
+    /// Store the version of the tokenizer used to index the documents
+    fn put_version(&mut self, version: u32);
+
+    /// Remove all the words, stems, facets and numbers postings, the ranking data and the vectors,
+    /// the documents are kept
+    fn clear_postings(&mut self);
+
+    /// Remove all the documents, without their postings
+    fn clear_documents(&mut self);
+
+    /// Rebuild all the postings from the documents, the documents are written again in case
+    /// their keys changed
+    fn reindex(&mut self) {
+        let documents: Vec<Document> = self
+            .get_documents()
+            .into_iter()
+            .map(Cow::into_owned)
+            .collect();
+        self.clear_postings();
+        self.clear_documents();
+        self.add_documents(documents);
+    }
+
+    /// Reindex the documents if they were indexed by an older version of the tokenizer
+    fn migrate(&mut self) {
+        let version = self.version();
+        if version != INDEXING_VERSION {
+            log::info!("Migrating the index from version {version} to {INDEXING_VERSION}");
+            self.reindex();
+            self.put_version(INDEXING_VERSION);
+        }
+    }
+
     /// Execute a search
-    fn search(&self, query: &Query) -> Vec<DocId>;
+    fn search(&self, query: &Query) -> Result<Vec<Hit>, SearchError> {
+        crate::search::execute(self, query)
+    }
+
+    /// Find the documents sharing the most significant words of a document,
+    /// `None` if the document doesn't exist
+    fn similar(&self, docid: DocId, filter: Option<&str>) -> Result<Option<Vec<Hit>>, SearchError> {
+        crate::search::similar(self, docid, filter)
+    }
+
+    /// Open the index `uid`, stored apart from the default index. It's created if it doesn't exist.
+    fn open(uid: &str) -> Self;
+
+    /// The uids of the indexes stored by `open`.
+    fn uids() -> Vec<String>;
 
     /// clear the on disk database
     fn clear_database();
 }
+
+/// The directory of the indexes opened by their uid, one directory per index.
+const INDEXES_DIR: &str = "indexes";
+
+/// The path of the database `db_name` of the index `uid`, its directory is created if needed.
+fn index_path(uid: &str, db_name: &str) -> PathBuf {
+    let dir = Path::new(INDEXES_DIR).join(uid);
+    std::fs::create_dir_all(&dir).expect("Can't create the directory of the index");
+    dir.join(db_name)
+}
+
+/// The uids of the indexes with a database `db_name`, the ones of the other engines are skipped.
+fn index_uids(db_name: &str) -> Vec<String> {
+    let entries = match std::fs::read_dir(INDEXES_DIR) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Vec::new(),
+        Err(e) => panic!("{e}"),
+    };
+    let mut uids: Vec<_> = entries
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.join(db_name).exists())
+        .filter_map(|path| Some(path.file_name()?.to_str()?.to_string()))
+        .collect();
+    uids.sort_unstable();
+    uids
+}
+
+/// An iterator over the documents of an index, see `Index::documents`.
+pub struct Documents<'a, I: Index> {
+    index: &'a I,
+    chunk_size: usize,
+    after: Option<DocId>,
+    chunk: std::vec::IntoIter<Cow<'a, Document>>,
+}
+
+impl<'a, I: Index> Iterator for Documents<'a, I> {
+    type Item = Cow<'a, Document>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(document) = self.chunk.next() {
+            return Some(document);
+        }
+        let chunk = self.index.documents_after(self.after, self.chunk_size);
+        self.after = Some(chunk.last()?.docid());
+        self.chunk = chunk.into_iter();
+        self.chunk.next()
+    }
+}
+
+/// The parts of a document needed to rank it, they are stored apart from the document
+/// so it doesn't have to be deserialized for every candidate of a search.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RankingData {
+    /// The words and stems of each boosted attribute.
+    pub attributes: BTreeMap<String, BTreeSet<String>>,
+    /// The value of each attribute of the custom ranking, if it's a number.
+    pub numbers: BTreeMap<String, f64>,
+    /// The values of each faceted attribute.
+    #[serde(default)]
+    pub facets: BTreeMap<String, Vec<String>>,
+    /// The position of the document, from its `_geo` attribute.
+    #[serde(default)]
+    pub geo: Option<Point>,
+}
+
+/// The key of the postings of a value of a faceted attribute,
+/// the geohash cells of the documents are stored as the values of `_geo`.
+pub fn facet_key(field: &str, value: &str) -> String {
+    format!("{field}\0{value}")
+}
+
+/// The values of an attribute as they are indexed in the facets, the strings are lowercased
+/// and the arrays are flattened. The objects and the nulls are ignored.
+pub fn facet_values(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(s) => vec![s.to_lowercase()],
+        Value::Number(n) => vec![n.to_string()],
+        Value::Bool(b) => vec![b.to_string()],
+        Value::Array(values) => values.iter().flat_map(facet_values).collect(),
+        Value::Null | Value::Object(_) => Vec::new(),
+    }
+}
+
+/// Extract from the documents everything they need to be indexed under.
+struct Extractor {
+    settings: Settings,
+    tokenizer: Arc<dyn Tokenizer>,
+}
+
+/// Everything a document needs to be indexed under.
+struct Extracted {
+    words: Vec<String>,
+    stems: Vec<String>,
+    /// The keys of the postings of the facets.
+    facets: Vec<String>,
+    /// The keys of the postings of the numbers.
+    numbers: Vec<String>,
+    ranking: RankingData,
+    /// The embedding of the document, if it has the dimensions of the settings.
+    vector: Option<Vec<f32>>,
+}
+
+impl Extractor {
+    fn new<I: Index>(index: &I) -> Self {
+        let settings = index.settings();
+        Extractor {
+            tokenizer: index.tokenizer(&settings),
+            settings,
+        }
+    }
+
+    fn extract(&self, document: &Document) -> Extracted {
+        let words = self.words(document.fields());
+        let stems = self.stems(&words);
+
+        let mut ranking = RankingData::default();
+        for attribute in self.settings.attribute_boosts.keys() {
+            let words = self.words(document.field(attribute));
+            let stems = self.stems(&words);
+            ranking
+                .attributes
+                .insert(attribute.clone(), words.into_iter().chain(stems).collect());
+        }
+        // the numbers of the custom ranking are indexed too, to score them against their bounds
+        let mut numbers = Vec::new();
+        for rule in &self.settings.custom_ranking {
+            if let Some(number) = document.get(&rule.field).and_then(Value::as_f64) {
+                ranking.numbers.insert(rule.field.clone(), number);
+                numbers.push(number_key(&rule.field, number));
+            }
+        }
+
+        let mut facets = Vec::new();
+        for field in self.settings.faceted() {
+            let value = match document.get(field) {
+                Some(value) => value,
+                None => continue,
+            };
+            let mut keys: Vec<_> = number_values(value)
+                .into_iter()
+                .map(|number| number_key(field, number))
+                .collect();
+            keys.sort_unstable();
+            keys.dedup();
+            numbers.extend(keys);
+
+            let mut values = facet_values(value);
+            values.sort_unstable();
+            values.dedup();
+            if !values.is_empty() {
+                facets.extend(values.iter().map(|value| facet_key(field, value)));
+                ranking.facets.insert(field.to_string(), values);
+            }
+        }
+
+        ranking.geo = document.get(GEO_FIELD).and_then(Point::from_value);
+        if let Some(point) = ranking.geo {
+            facets.extend(point.cells().iter().map(|cell| facet_key(GEO_FIELD, cell)));
+            numbers.push(number_key(GEO_LAT_FIELD, point.lat));
+            numbers.push(number_key(GEO_LNG_FIELD, point.lng));
+        }
+        numbers.sort_unstable();
+        numbers.dedup();
+
+        let vector = self
+            .settings
+            .vector_dimensions
+            .and_then(|dimensions| parse_vector(document.get(VECTORS_FIELD)?, dimensions).ok());
+
+        Extracted {
+            words,
+            stems,
+            facets,
+            numbers,
+            ranking,
+            vector,
+        }
+    }
+
+    fn words<'a>(&self, fields: impl Iterator<Item = &'a str>) -> Vec<String> {
+        let mut words: Vec<_> = fields
+            .flat_map(|field| self.tokenizer.tokenize(field))
+            .filter(Token::is_word)
+            .flat_map(|token| {
+                iter::once(token.normalized)
+                    .chain(token.accented)
+                    .chain(token.sub_words)
+            })
+            .collect();
+        // if a word is present multiple times in the same field we only count it once
+        words.sort_unstable();
+        words.dedup();
+        words
+    }
+
+    fn stems(&self, words: &[String]) -> Vec<String> {
+        let mut stems: Vec<_> = match self.settings.language {
+            Some(language) => words.iter().map(|word| language.stem(word)).collect(),
+            None => Vec::new(),
+        };
+        stems.sort_unstable();
+        stems.dedup();
+        stems
+    }
+}
+
+/// The postings modified by a batch of documents, they are written all at once
+/// at the end of the batch.
+#[derive(Default)]
+struct DirtyPostings {
+    words: HashMap<String, RoaringBitmap>,
+    stems: HashMap<String, RoaringBitmap>,
+    facets: HashMap<String, RoaringBitmap>,
+    numbers: HashMap<String, RoaringBitmap>,
+}
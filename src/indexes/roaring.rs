@@ -1,16 +1,22 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs::File,
     io::{BufReader, ErrorKind, Seek, SeekFrom},
+    ops::Bound,
+    path::Path,
+    sync::Arc,
 };
 
 use roaring::RoaringBitmap;
 use serde::{Deserialize, Serialize};
 
-use crate::{tokenize, DocId, Document, Query};
+use crate::{
+    numbers::{key_number, key_range, NumberRange},
+    DocId, Document, Rules, Settings, Tokenizer,
+};
 
-use super::Index;
+use super::{facet_key, index_path, index_uids, Extracted, Extractor, Index, RankingData};
 
 const DB_NAME: &str = "roaring.db";
 
@@ -18,12 +24,29 @@ const DB_NAME: &str = "roaring.db";
 pub struct Roaring {
     inner: Inner,
     file: File,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Inner {
-    documents: HashMap<DocId, Document>,
-    words: HashMap<String, RoaringBitmap>,
+    documents: BTreeMap<DocId, Document>,
+    words: BTreeMap<String, RoaringBitmap>,
+    #[serde(default)]
+    stems: BTreeMap<String, RoaringBitmap>,
+    #[serde(default)]
+    facets: BTreeMap<String, RoaringBitmap>,
+    #[serde(default)]
+    numbers: BTreeMap<String, RoaringBitmap>,
+    #[serde(default)]
+    ranking: HashMap<DocId, RankingData>,
+    #[serde(default)]
+    vectors: HashMap<DocId, Vec<f32>>,
+    #[serde(default)]
+    settings: Settings,
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    version: u32,
 }
 
 impl Roaring {
@@ -34,53 +57,100 @@ impl Roaring {
             .expect("Internal error, can't serialize document");
     }
 
-    fn add_document(&mut self, document: Document) {
+    fn add_document(&mut self, document: Document, extractor: &Extractor) {
         let docid = document.docid();
 
         // first we delete the old version of the document
-        self.delete_document(docid);
-
-        let mut words: Vec<_> = document.fields().flat_map(tokenize).collect();
-        // if a word is present multiple times in the same field we only count it once
-        words.sort_unstable();
-        words.dedup();
+        self.delete_document(docid, extractor);
 
+        let Extracted {
+            words,
+            stems,
+            facets,
+            numbers,
+            ranking,
+            vector,
+        } = extractor.extract(&document);
         for word in words {
-            self.inner
-                .words
-                .entry(word.to_string())
-                .or_default()
-                .insert(docid);
+            self.inner.words.entry(word).or_default().insert(docid);
+        }
+        for stem in stems {
+            self.inner.stems.entry(stem).or_default().insert(docid);
+        }
+        for facet in facets {
+            self.inner.facets.entry(facet).or_default().insert(docid);
+        }
+        for number in numbers {
+            self.inner.numbers.entry(number).or_default().insert(docid);
+        }
+        self.inner.ranking.insert(docid, ranking);
+        if let Some(vector) = vector {
+            self.inner.vectors.insert(docid, vector);
         }
         self.inner.documents.insert(docid, document);
     }
 
-    fn delete_document(&mut self, docid: DocId) {
+    fn delete_document(&mut self, docid: DocId, extractor: &Extractor) {
         if let Some(document) = self.inner.documents.remove(&docid) {
+            self.inner.ranking.remove(&docid);
+            self.inner.vectors.remove(&docid);
             // we get all the words in a document and then get rids of our id for each of these words
-            let mut words: Vec<_> = document.fields().flat_map(tokenize).collect();
-            // if a word is present multiple times in the same field we only count it once
-            words.sort_unstable();
-            words.dedup();
+            let Extracted {
+                words,
+                stems,
+                facets,
+                numbers,
+                ..
+            } = extractor.extract(&document);
 
             words.into_iter().for_each(|word| {
                 self.inner.words.get_mut(&word).map(|ids| ids.remove(docid));
             });
+            stems.into_iter().for_each(|stem| {
+                self.inner.stems.get_mut(&stem).map(|ids| ids.remove(docid));
+            });
+            facets.into_iter().for_each(|facet| {
+                self.inner
+                    .facets
+                    .get_mut(&facet)
+                    .map(|ids| ids.remove(docid));
+            });
+            numbers.into_iter().for_each(|number| {
+                self.inner
+                    .numbers
+                    .get_mut(&number)
+                    .map(|ids| ids.remove(docid));
+            });
         }
     }
 }
 
 impl Default for Roaring {
     fn default() -> Self {
-        let mut file = match File::open(DB_NAME) {
+        Self::open_path(Path::new(DB_NAME))
+    }
+}
+
+impl Roaring {
+    fn open_path(path: &Path) -> Self {
+        let mut file = match File::open(path) {
             Ok(file) => file,
             Err(err) if err.kind() == ErrorKind::NotFound => {
                 let mut index = Roaring {
                     inner: Inner {
-                        documents: HashMap::new(),
-                        words: HashMap::new(),
+                        documents: BTreeMap::new(),
+                        words: BTreeMap::new(),
+                        stems: BTreeMap::new(),
+                        facets: BTreeMap::new(),
+                        numbers: BTreeMap::new(),
+                        ranking: HashMap::new(),
+                        vectors: HashMap::new(),
+                        settings: Settings::default(),
+                        rules: Rules::new(),
+                        version: 0,
                     },
-                    file: File::create(DB_NAME).expect("Can't open database"),
+                    file: File::create(path).expect("Can't open database"),
+                    tokenizer: None,
                 };
                 index.persist();
                 return index;
@@ -89,9 +159,13 @@ impl Default for Roaring {
         };
         let mut reader = BufReader::new(&mut file);
         let inner = serde_json::from_reader(&mut reader).expect("Corrupted database");
-        let file = File::create(DB_NAME).expect("Can't write in database");
+        let file = File::create(path).expect("Can't write in database");
 
-        let mut this = Self { inner, file };
+        let mut this = Self {
+            inner,
+            file,
+            tokenizer: None,
+        };
         this.persist();
         this
     }
@@ -110,27 +184,167 @@ impl Index for Roaring {
         self.inner.documents.get(&id).map(Cow::Borrowed)
     }
 
+    fn documents_after(&self, after: Option<DocId>, limit: usize) -> Vec<Cow<Document>> {
+        let start = after.map_or(Bound::Unbounded, Bound::Excluded);
+        self.inner
+            .documents
+            .range((start, Bound::Unbounded))
+            .take(limit)
+            .map(|(_, document)| Cow::Borrowed(document))
+            .collect()
+    }
+
+    fn nb_documents(&self) -> u64 {
+        self.inner.documents.len() as u64
+    }
+
     fn add_documents(&mut self, document: Vec<Document>) {
+        let extractor = Extractor::new(self);
         document
             .into_iter()
-            .for_each(|document| self.add_document(document));
+            .for_each(|document| self.add_document(document, &extractor));
 
         self.persist()
     }
 
     fn delete_documents(&mut self, docids: Vec<DocId>) {
+        let extractor = Extractor::new(self);
         for docid in docids {
-            self.delete_document(docid);
+            self.delete_document(docid, &extractor);
         }
         self.persist();
     }
 
-    fn search(&self, query: &Query) -> Vec<DocId> {
-        let docids = tokenize(query.q.as_deref().unwrap_or(""))
-            .filter_map(|word| self.inner.words.get(&word))
-            .fold(RoaringBitmap::default(), |acc, bitmap| acc | bitmap);
+    fn word_docids(&self, word: &str) -> RoaringBitmap {
+        self.inner.words.get(word).cloned().unwrap_or_default()
+    }
+
+    fn contains_word(&self, word: &str) -> bool {
+        self.inner
+            .words
+            .get(word)
+            .is_some_and(|docids| !docids.is_empty())
+    }
+
+    fn words_starting_with(&self, prefix: &str) -> Vec<String> {
+        self.inner
+            .words
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|(word, _)| word.starts_with(prefix))
+            .filter(|(_, docids)| !docids.is_empty())
+            .map(|(word, _)| word.clone())
+            .collect()
+    }
+
+    fn word_frequencies(&self) -> Vec<(String, u64)> {
+        self.inner
+            .words
+            .iter()
+            .filter(|(_, docids)| !docids.is_empty())
+            .map(|(word, docids)| (word.clone(), docids.len()))
+            .collect()
+    }
+
+    fn stem_docids(&self, stem: &str) -> RoaringBitmap {
+        self.inner.stems.get(stem).cloned().unwrap_or_default()
+    }
+
+    fn facet_docids(&self, field: &str, value: &str) -> RoaringBitmap {
+        self.inner
+            .facets
+            .get(&facet_key(field, value))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn number_postings(&self, field: &str, range: NumberRange) -> Vec<(f64, RoaringBitmap)> {
+        self.inner
+            .numbers
+            .range(key_range(field, range))
+            .filter(|(_, docids)| !docids.is_empty())
+            .map(|(key, docids)| (key_number(key), docids.clone()))
+            .collect()
+    }
+
+    fn number_bounds(&self, field: &str) -> Option<(f64, f64)> {
+        let mut numbers = self
+            .inner
+            .numbers
+            .range(key_range(field, (Bound::Unbounded, Bound::Unbounded)))
+            .filter(|(_, docids)| !docids.is_empty())
+            .map(|(key, _)| key_number(key));
+        let min = numbers.next()?;
+        Some((min, numbers.next_back().unwrap_or(min)))
+    }
+
+    fn ranking_data(&self, docid: DocId) -> RankingData {
+        self.inner.ranking.get(&docid).cloned().unwrap_or_default()
+    }
+
+    fn vectors(&self) -> Vec<(DocId, Vec<f32>)> {
+        self.inner
+            .vectors
+            .iter()
+            .map(|(docid, vector)| (*docid, vector.clone()))
+            .collect()
+    }
+
+    fn settings(&self) -> Settings {
+        self.inner.settings.clone()
+    }
+
+    fn put_settings(&mut self, settings: Settings) {
+        self.inner.settings = settings;
+        self.persist();
+    }
+
+    fn curation_rules(&self) -> Rules {
+        self.inner.rules.clone()
+    }
+
+    fn put_curation_rules(&mut self, rules: Rules) {
+        self.inner.rules = rules;
+        self.persist();
+    }
+
+    fn custom_tokenizer(&self) -> Option<Arc<dyn Tokenizer>> {
+        self.tokenizer.clone()
+    }
+
+    fn set_tokenizer(&mut self, tokenizer: Arc<dyn Tokenizer>) {
+        self.tokenizer = Some(tokenizer);
+    }
+
+    fn version(&self) -> u32 {
+        self.inner.version
+    }
+
+    fn put_version(&mut self, version: u32) {
+        self.inner.version = version;
+        self.persist();
+    }
+
+    fn clear_postings(&mut self) {
+        self.inner.words.clear();
+        self.inner.stems.clear();
+        self.inner.facets.clear();
+        self.inner.numbers.clear();
+        self.inner.ranking.clear();
+        self.inner.vectors.clear();
+        self.persist();
+    }
+
+    fn clear_documents(&mut self) {
+        self.inner.documents.clear();
+        self.persist();
+    }
+
+    fn open(uid: &str) -> Self {
+        Self::open_path(&index_path(uid, DB_NAME))
+    }
 
-        docids.into_iter().collect()
+    fn uids() -> Vec<String> {
+        index_uids(DB_NAME)
     }
 
     fn clear_database() {
@@ -2,170 +2,425 @@ use std::{
     borrow::Cow,
     collections::{hash_map::Entry, HashMap},
     io::ErrorKind,
+    ops::Bound,
+    path::Path,
+    sync::Arc,
 };
 
 use roaring::RoaringBitmap;
 
-use crate::{tokenize, DocId, Document, Query};
+use crate::{
+    numbers::{key_number, key_range, NumberRange},
+    DocId, Document, Rules, Settings, Tokenizer,
+};
 
-use super::Index;
+use super::{
+    facet_key, index_path, index_uids, DirtyPostings, Extracted, Extractor, Index, RankingData,
+};
 
 const DB_NAME: &str = "sled.db";
 
+/// Keys of the entries stored in the main database.
+mod main_key {
+    pub const SETTINGS: &str = "settings";
+    pub const RULES: &str = "rules";
+    pub const VERSION: &str = "version";
+}
+
 #[derive(Debug)]
 pub struct Sled {
     documents: sled::Db,
     words: sled::Db,
+    stems: sled::Db,
+    facets: sled::Db,
+    numbers: sled::Db,
+    ranking: sled::Db,
+    vectors: sled::Db,
+    main: sled::Db,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
 }
 
 impl Sled {
     fn add_document(
         &mut self,
         document: Document,
-        dirty_words: &mut HashMap<String, RoaringBitmap>,
+        extractor: &Extractor,
+        dirty: &mut DirtyPostings,
     ) {
         let docid = document.docid();
 
         // first we delete the old version of the document
-        self.delete_document(docid, dirty_words);
-
-        let mut words: Vec<_> = document.fields().flat_map(tokenize).collect();
-        // if a word is present multiple times in the same field we only count it once
-        words.sort_unstable();
-        words.dedup();
+        self.delete_document(docid, extractor, dirty);
 
-        for word in words.into_iter().filter(|w| !w.is_empty()) {
-            match dirty_words.entry(word) {
-                Entry::Occupied(mut bitmap) => {
-                    bitmap.get_mut().insert(docid);
-                }
-                Entry::Vacant(entry) => {
-                    // We get the current value from the db
-                    let mut bitmap = match self.words.get(entry.key()).unwrap() {
-                        Some(bytes) => RoaringBitmap::deserialize_from(&*bytes).unwrap(),
-                        None => RoaringBitmap::new(),
-                    };
-                    bitmap.insert(docid);
-                    entry.insert(bitmap);
-                }
-            };
+        let Extracted {
+            words,
+            stems,
+            facets,
+            numbers,
+            ranking,
+            vector,
+        } = extractor.extract(&document);
+        for word in words {
+            Self::dirty_bitmap(&self.words, &mut dirty.words, word).insert(docid);
+        }
+        for stem in stems {
+            Self::dirty_bitmap(&self.stems, &mut dirty.stems, stem).insert(docid);
+        }
+        for facet in facets {
+            Self::dirty_bitmap(&self.facets, &mut dirty.facets, facet).insert(docid);
+        }
+        for number in numbers {
+            Self::dirty_bitmap(&self.numbers, &mut dirty.numbers, number).insert(docid);
+        }
+        self.ranking
+            .insert(docid.to_ne_bytes(), serde_json::to_vec(&ranking).unwrap())
+            .unwrap();
+        if let Some(vector) = vector {
+            self.vectors
+                .insert(docid.to_ne_bytes(), serde_json::to_vec(&vector).unwrap())
+                .unwrap();
         }
         self.documents
-            .insert(docid.to_ne_bytes(), serde_json::to_vec(&document).unwrap())
+            .insert(docid.to_be_bytes(), serde_json::to_vec(&document).unwrap())
             .unwrap();
     }
 
-    fn delete_document(&mut self, docid: DocId, dirty_words: &mut HashMap<String, RoaringBitmap>) {
-        if let Some(document) = self.documents.remove(docid.to_ne_bytes()).unwrap() {
+    fn delete_document(&mut self, docid: DocId, extractor: &Extractor, dirty: &mut DirtyPostings) {
+        if let Some(document) = self.documents.remove(docid.to_be_bytes()).unwrap() {
+            self.ranking.remove(docid.to_ne_bytes()).unwrap();
+            self.vectors.remove(docid.to_ne_bytes()).unwrap();
             let document: Document =
                 serde_json::from_slice(&document).expect("Can't parse document");
             // we get all the words in a document and then get rids of our id for each of these words
-            let mut words: Vec<_> = document.fields().flat_map(tokenize).collect();
-            // if a word is present multiple times in the same field we only count it once
-            words.sort_unstable();
-            words.dedup();
-
-            for word in words.into_iter().filter(|w| !w.is_empty()) {
-                match dirty_words.entry(word) {
-                    Entry::Occupied(mut bitmap) => {
-                        bitmap.get_mut().insert(docid);
-                    }
-                    Entry::Vacant(entry) => {
-                        // We get the current value from the db
-                        let bytes = self.words.get(entry.key()).unwrap().unwrap();
-                        let mut bitmap =
-                            roaring::RoaringBitmap::deserialize_from(bytes.as_ref()).unwrap();
-                        bitmap.remove(docid);
-                        entry.insert(bitmap);
-                    }
-                }
+            let Extracted {
+                words,
+                stems,
+                facets,
+                numbers,
+                ..
+            } = extractor.extract(&document);
+            for word in words {
+                Self::dirty_bitmap(&self.words, &mut dirty.words, word).remove(docid);
+            }
+            for stem in stems {
+                Self::dirty_bitmap(&self.stems, &mut dirty.stems, stem).remove(docid);
+            }
+            for facet in facets {
+                Self::dirty_bitmap(&self.facets, &mut dirty.facets, facet).remove(docid);
+            }
+            for number in numbers {
+                Self::dirty_bitmap(&self.numbers, &mut dirty.numbers, number).remove(docid);
+            }
+        }
+    }
+
+    /// Get the bitmap of a key from the dirty postings,
+    /// the first time a key is modified its bitmap is fetched from the database.
+    fn dirty_bitmap<'a>(
+        database: &sled::Db,
+        dirty: &'a mut HashMap<String, RoaringBitmap>,
+        key: String,
+    ) -> &'a mut RoaringBitmap {
+        match dirty.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                // We get the current value from the db
+                let bitmap = match database.get(entry.key()).unwrap() {
+                    Some(bytes) => RoaringBitmap::deserialize_from(&*bytes).unwrap(),
+                    None => RoaringBitmap::new(),
+                };
+                entry.insert(bitmap)
             }
         }
     }
 
-    /// Update all the entry in the dirty words.
-    fn apply_dirty_words(&mut self, dirty_words: &mut HashMap<String, RoaringBitmap>) {
-        // we reuse the same allocation for all the documents
+    /// Update all the entries in the dirty postings.
+    fn apply_dirty_postings(&mut self, dirty: DirtyPostings) {
+        // we reuse the same allocation for all the bitmaps
         let mut buffer = Vec::new();
 
-        for (word, bitmap) in dirty_words.into_iter() {
-            bitmap.serialize_into(&mut buffer).unwrap();
-            self.words.insert(word, buffer.as_slice()).unwrap();
-            buffer.clear();
+        for (database, postings) in [
+            (&self.words, dirty.words),
+            (&self.stems, dirty.stems),
+            (&self.facets, dirty.facets),
+            (&self.numbers, dirty.numbers),
+        ] {
+            for (key, bitmap) in postings {
+                if bitmap.is_empty() {
+                    database.remove(key).unwrap();
+                } else {
+                    bitmap.serialize_into(&mut buffer).unwrap();
+                    database.insert(key, buffer.as_slice()).unwrap();
+                    buffer.clear();
+                }
+            }
+            database.flush().unwrap();
         }
-
-        self.words.flush().unwrap();
     }
 }
 
 impl Default for Sled {
     fn default() -> Self {
-        match std::fs::create_dir(DB_NAME) {
+        Self::open_path(Path::new(DB_NAME))
+    }
+}
+
+impl Sled {
+    fn open_path(path: &Path) -> Self {
+        match std::fs::create_dir(path) {
             Ok(()) => (),
             Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => (),
             e => e.unwrap(),
         }
-        let doc_mapping: sled::Db = sled::open(format!("{DB_NAME}/doc_mapping.db")).unwrap();
-        let words: sled::Db = sled::open(format!("{DB_NAME}/words.db")).unwrap();
+        let doc_mapping: sled::Db = sled::open(path.join("doc_mapping.db")).unwrap();
+        let words: sled::Db = sled::open(path.join("words.db")).unwrap();
+        let stems: sled::Db = sled::open(path.join("stems.db")).unwrap();
+        let facets: sled::Db = sled::open(path.join("facets.db")).unwrap();
+        let numbers: sled::Db = sled::open(path.join("numbers.db")).unwrap();
+        let ranking: sled::Db = sled::open(path.join("ranking.db")).unwrap();
+        let vectors: sled::Db = sled::open(path.join("vectors.db")).unwrap();
+        let main: sled::Db = sled::open(path.join("main.db")).unwrap();
         Self {
             documents: doc_mapping,
             words,
+            stems,
+            facets,
+            numbers,
+            ranking,
+            vectors,
+            main,
+            tokenizer: None,
         }
     }
 }
 
 impl Index for Sled {
     fn get_documents(&self) -> Vec<Cow<Document>> {
+        // the documents are read without their keys, they may come from an older version
         self.documents
             .iter()
-            .map(|id| {
-                self.get_document(u32::from_ne_bytes((*id.unwrap().0).try_into().unwrap()))
-                    .expect("Corrupted database")
-                    .clone()
-            })
+            .values()
+            .map(|doc| Cow::Owned(serde_json::from_slice(&doc.unwrap()).unwrap()))
             .collect()
     }
 
     fn get_document(&self, id: DocId) -> Option<Cow<Document>> {
         self.documents
-            .get(&id.to_ne_bytes())
+            .get(id.to_be_bytes())
             .unwrap()
             .map(|doc| serde_json::from_slice(&doc).unwrap())
             .map(Cow::Owned)
     }
 
+    /// The documents are keyed by their big-endian ids to be sorted by id.
+    fn documents_after(&self, after: Option<DocId>, limit: usize) -> Vec<Cow<Document>> {
+        let start = after.map_or(Bound::Unbounded, |after| {
+            Bound::Excluded(after.to_be_bytes())
+        });
+        self.documents
+            .range((start, Bound::Unbounded))
+            .take(limit)
+            .map(|entry| serde_json::from_slice(&entry.unwrap().1).unwrap())
+            .map(Cow::Owned)
+            .collect()
+    }
+
+    fn nb_documents(&self) -> u64 {
+        self.documents.len() as u64
+    }
+
     /// Since deser+ser the bitmaps for each insertion is slow we're going to create a
     /// temporary map of the words databases containing only the modified words.
     /// Then we reinsert + serialize all the modified words at once.
     fn add_documents(&mut self, documents: Vec<Document>) {
-        let mut dirty_words = HashMap::new();
+        let extractor = Extractor::new(self);
+        let mut dirty = DirtyPostings::default();
 
         documents
             .into_iter()
-            .for_each(|document| self.add_document(document, &mut dirty_words));
+            .for_each(|document| self.add_document(document, &extractor, &mut dirty));
 
-        self.apply_dirty_words(&mut dirty_words);
+        self.apply_dirty_postings(dirty);
+        self.ranking.flush().unwrap();
+        self.vectors.flush().unwrap();
         self.documents.flush().unwrap();
     }
 
     fn delete_documents(&mut self, docids: Vec<DocId>) {
-        let mut dirty_words = HashMap::new();
+        let extractor = Extractor::new(self);
+        let mut dirty = DirtyPostings::default();
 
         for docid in docids {
-            self.delete_document(docid, &mut dirty_words);
+            self.delete_document(docid, &extractor, &mut dirty);
         }
-        self.apply_dirty_words(&mut dirty_words);
+        self.apply_dirty_postings(dirty);
+        self.ranking.flush().unwrap();
+        self.vectors.flush().unwrap();
         self.documents.flush().unwrap();
     }
 
-    fn search(&self, query: &Query) -> Vec<DocId> {
-        let docids = tokenize(query.q.as_deref().unwrap_or(""))
-            .filter_map(|word| self.words.get(&word).unwrap())
-            .fold(RoaringBitmap::default(), |acc, bitmap| {
-                acc | RoaringBitmap::deserialize_from(&*bitmap).unwrap()
-            });
+    fn word_docids(&self, word: &str) -> RoaringBitmap {
+        match self.words.get(word).unwrap() {
+            Some(bytes) => RoaringBitmap::deserialize_from(&*bytes).unwrap(),
+            None => RoaringBitmap::new(),
+        }
+    }
+
+    fn contains_word(&self, word: &str) -> bool {
+        // the empty bitmaps are deleted, we don't need to decode it
+        self.words.contains_key(word).unwrap()
+    }
+
+    fn words_starting_with(&self, prefix: &str) -> Vec<String> {
+        self.words
+            .scan_prefix(prefix)
+            .keys()
+            .map(|word| String::from_utf8(word.unwrap().to_vec()).unwrap())
+            .collect()
+    }
+
+    fn word_frequencies(&self) -> Vec<(String, u64)> {
+        self.words
+            .iter()
+            .map(|entry| {
+                let (word, bytes) = entry.unwrap();
+                let docids = RoaringBitmap::deserialize_from(&*bytes).unwrap();
+                (String::from_utf8(word.to_vec()).unwrap(), docids.len())
+            })
+            .collect()
+    }
+
+    fn stem_docids(&self, stem: &str) -> RoaringBitmap {
+        match self.stems.get(stem).unwrap() {
+            Some(bytes) => RoaringBitmap::deserialize_from(&*bytes).unwrap(),
+            None => RoaringBitmap::new(),
+        }
+    }
+
+    fn facet_docids(&self, field: &str, value: &str) -> RoaringBitmap {
+        match self.facets.get(facet_key(field, value)).unwrap() {
+            Some(bytes) => RoaringBitmap::deserialize_from(&*bytes).unwrap(),
+            None => RoaringBitmap::new(),
+        }
+    }
+
+    fn number_postings(&self, field: &str, range: NumberRange) -> Vec<(f64, RoaringBitmap)> {
+        self.numbers
+            .range(key_range(field, range))
+            .map(|entry| {
+                let (key, bytes) = entry.unwrap();
+                let key = std::str::from_utf8(&key).expect("Corrupted database");
+                (
+                    key_number(key),
+                    RoaringBitmap::deserialize_from(&*bytes).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn number_bounds(&self, field: &str) -> Option<(f64, f64)> {
+        let mut numbers = self
+            .numbers
+            .range(key_range(field, (Bound::Unbounded, Bound::Unbounded)))
+            .keys()
+            .map(|key| key_number(std::str::from_utf8(&key.unwrap()).expect("Corrupted database")));
+        let min = numbers.next()?;
+        Some((min, numbers.next_back().unwrap_or(min)))
+    }
+
+    fn ranking_data(&self, docid: DocId) -> RankingData {
+        self.ranking
+            .get(docid.to_ne_bytes())
+            .unwrap()
+            .map(|ranking| serde_json::from_slice(&ranking).unwrap())
+            .unwrap_or_default()
+    }
+
+    fn vectors(&self) -> Vec<(DocId, Vec<f32>)> {
+        self.vectors
+            .iter()
+            .map(|entry| {
+                let (docid, vector) = entry.unwrap();
+                (
+                    DocId::from_ne_bytes((*docid).try_into().unwrap()),
+                    serde_json::from_slice(&vector).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn settings(&self) -> Settings {
+        self.main
+            .get(main_key::SETTINGS)
+            .unwrap()
+            .map(|settings| serde_json::from_slice(&settings).unwrap())
+            .unwrap_or_default()
+    }
+
+    fn put_settings(&mut self, settings: Settings) {
+        self.main
+            .insert(main_key::SETTINGS, serde_json::to_vec(&settings).unwrap())
+            .unwrap();
+        self.main.flush().unwrap();
+    }
+
+    fn curation_rules(&self) -> Rules {
+        self.main
+            .get(main_key::RULES)
+            .unwrap()
+            .map(|rules| serde_json::from_slice(&rules).unwrap())
+            .unwrap_or_default()
+    }
+
+    fn put_curation_rules(&mut self, rules: Rules) {
+        self.main
+            .insert(main_key::RULES, serde_json::to_vec(&rules).unwrap())
+            .unwrap();
+        self.main.flush().unwrap();
+    }
+
+    fn custom_tokenizer(&self) -> Option<Arc<dyn Tokenizer>> {
+        self.tokenizer.clone()
+    }
+
+    fn set_tokenizer(&mut self, tokenizer: Arc<dyn Tokenizer>) {
+        self.tokenizer = Some(tokenizer);
+    }
+
+    fn version(&self) -> u32 {
+        self.main
+            .get(main_key::VERSION)
+            .unwrap()
+            .map(|version| u32::from_ne_bytes((*version).try_into().unwrap()))
+            .unwrap_or_default()
+    }
+
+    fn put_version(&mut self, version: u32) {
+        self.main
+            .insert(main_key::VERSION, &version.to_ne_bytes())
+            .unwrap();
+        self.main.flush().unwrap();
+    }
+
+    fn clear_postings(&mut self) {
+        self.words.clear().unwrap();
+        self.stems.clear().unwrap();
+        self.facets.clear().unwrap();
+        self.numbers.clear().unwrap();
+        self.ranking.clear().unwrap();
+        self.vectors.clear().unwrap();
+    }
+
+    fn clear_documents(&mut self) {
+        self.documents.clear().unwrap();
+    }
+
+    fn open(uid: &str) -> Self {
+        Self::open_path(&index_path(uid, DB_NAME))
+    }
 
-        docids.into_iter().collect()
+    fn uids() -> Vec<String> {
+        index_uids(DB_NAME)
     }
 
     fn clear_database() {
@@ -1,18 +1,29 @@
-use std::sync::Mutex;
+use std::{
+    ops::Bound,
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
-use rusqlite::{params, Connection};
+use roaring::RoaringBitmap;
+use rusqlite::{params, Connection, OptionalExtension};
 
-use crate::{tokenize, DocId, Index};
+use crate::{
+    numbers::{key_number, key_range, NumberRange},
+    Index, Rules, Settings, Tokenizer,
+};
 
-lazy_static::lazy_static!(
-    static ref CONNECTION: Mutex<Connection> = Mutex::new(Connection::open("sqlite.db").expect("Couldn’t init sqlite database"));
-);
+use super::{facet_key, index_path, index_uids, Extracted, Extractor, RankingData};
 
-pub struct SQLite {}
+const DB_NAME: &str = "sqlite.db";
+
+pub struct SQLite {
+    connection: Mutex<Connection>,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
+}
 
 impl Index for SQLite {
     fn get_documents(&self) -> Vec<std::borrow::Cow<crate::Document>> {
-        let connection = CONNECTION.lock().unwrap();
+        let connection = self.connection.lock().unwrap();
         let res = connection
             .prepare(
                 r#"
@@ -35,7 +46,7 @@ impl Index for SQLite {
     }
 
     fn get_document(&self, id: crate::DocId) -> Option<std::borrow::Cow<crate::Document>> {
-        let connection = CONNECTION.lock().unwrap();
+        let connection = self.connection.lock().unwrap();
         let res = connection
             .prepare("SELECT document FROM documents WHERE doc_id = ?1;")
             .unwrap()
@@ -44,14 +55,51 @@ impl Index for SQLite {
                     &row.get::<_, Vec<u8>>(0).expect("Error retrieving row"),
                 ))
             })
-            .unwrap()
+            .optional()
+            .unwrap()?
             .unwrap();
 
         Some(std::borrow::Cow::Owned(res))
     }
 
+    fn documents_after(
+        &self,
+        after: Option<crate::DocId>,
+        limit: usize,
+    ) -> Vec<std::borrow::Cow<crate::Document>> {
+        let connection = self.connection.lock().unwrap();
+        let res = connection
+            .prepare(
+                r#"
+        SELECT document FROM documents WHERE doc_id > ?1 ORDER BY doc_id LIMIT ?2;
+        "#,
+            )
+            .unwrap()
+            .query_map(params![after.map_or(-1, i64::from), limit as i64], |row| {
+                Ok(serde_json::from_slice(
+                    &row.get::<_, Vec<u8>>(0).expect("Error retrieving document"),
+                ))
+            })
+            .unwrap()
+            .map(Result::unwrap)
+            .map(Result::unwrap)
+            .map(std::borrow::Cow::Owned)
+            .collect();
+
+        res
+    }
+
+    fn nb_documents(&self) -> u64 {
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM documents;", [], |row| row.get(0))
+            .unwrap()
+    }
+
     fn add_documents(&mut self, documents: Vec<crate::Document>) {
-        let connection = CONNECTION.lock().unwrap();
+        let extractor = Extractor::new(self);
+        let connection = self.connection.lock().unwrap();
         let mut doc_stmt = connection
             .prepare(
                 r#"
@@ -67,6 +115,50 @@ impl Index for SQLite {
             "#,
             )
             .unwrap();
+        let mut stem_stmt = connection
+            .prepare(
+                r#"
+            INSERT INTO document_stems (doc_id, stem) VALUES (?, ?);
+            "#,
+            )
+            .unwrap();
+        let mut facet_stmt = connection
+            .prepare(
+                r#"
+            INSERT INTO document_facets (doc_id, facet) VALUES (?, ?);
+            "#,
+            )
+            .unwrap();
+        let mut number_stmt = connection
+            .prepare(
+                r#"
+            INSERT INTO document_numbers (doc_id, number) VALUES (?, ?);
+            "#,
+            )
+            .unwrap();
+        let mut ranking_stmt = connection
+            .prepare(
+                r#"
+            INSERT INTO ranking (doc_id, data) VALUES (?, ?)
+            ON CONFLICT(doc_id) DO UPDATE SET data = excluded.data;
+            "#,
+            )
+            .unwrap();
+        let mut vector_stmt = connection
+            .prepare(
+                r#"
+            INSERT INTO vectors (doc_id, vector) VALUES (?, ?)
+            ON CONFLICT(doc_id) DO UPDATE SET vector = excluded.vector;
+            "#,
+            )
+            .unwrap();
+        let mut del_vector_stmt = connection
+            .prepare(
+                r#"
+            DELETE FROM vectors WHERE doc_id = ?;
+            "#,
+            )
+            .unwrap();
         let mut del_search_stmt = connection
             .prepare(
                 r#"
@@ -74,69 +166,434 @@ impl Index for SQLite {
             "#,
             )
             .unwrap();
+        let mut del_stem_stmt = connection
+            .prepare(
+                r#"
+            DELETE FROM document_stems WHERE doc_id = ?;
+            "#,
+            )
+            .unwrap();
+        let mut del_facet_stmt = connection
+            .prepare(
+                r#"
+            DELETE FROM document_facets WHERE doc_id = ?;
+            "#,
+            )
+            .unwrap();
+        let mut del_number_stmt = connection
+            .prepare(
+                r#"
+            DELETE FROM document_numbers WHERE doc_id = ?;
+            "#,
+            )
+            .unwrap();
         for document in &documents {
             let doc_bytes = serde_json::to_vec(document).expect("Error while serializing document");
             let doc_id = document.docid();
             del_search_stmt
                 .execute(params![doc_id])
                 .expect("Error while deleting previous search");
+            del_stem_stmt
+                .execute(params![doc_id])
+                .expect("Error while deleting previous stems");
+            del_facet_stmt
+                .execute(params![doc_id])
+                .expect("Error while deleting previous facets");
+            del_number_stmt
+                .execute(params![doc_id])
+                .expect("Error while deleting previous numbers");
+            del_vector_stmt
+                .execute(params![doc_id])
+                .expect("Error while deleting previous vector");
             doc_stmt
                 .execute(params![doc_id, doc_bytes])
                 .expect("Error while inserting document");
-            document.fields().flat_map(tokenize).for_each(|word| {
+            let Extracted {
+                words,
+                stems,
+                facets,
+                numbers,
+                ranking,
+                vector,
+            } = extractor.extract(document);
+            let ranking = serde_json::to_vec(&ranking).expect("Error while serializing ranking");
+            ranking_stmt
+                .execute(params![doc_id, ranking])
+                .expect("Error while inserting ranking");
+            if let Some(vector) = vector {
+                let vector = serde_json::to_vec(&vector).expect("Error while serializing vector");
+                vector_stmt
+                    .execute(params![doc_id, vector])
+                    .expect("Error while inserting vector");
+            }
+            words.into_iter().for_each(|word| {
                 search_stmt.execute(params![doc_id, word]).unwrap();
             });
+            stems.into_iter().for_each(|stem| {
+                stem_stmt.execute(params![doc_id, stem]).unwrap();
+            });
+            facets.into_iter().for_each(|facet| {
+                facet_stmt.execute(params![doc_id, facet]).unwrap();
+            });
+            numbers.into_iter().for_each(|number| {
+                number_stmt.execute(params![doc_id, number]).unwrap();
+            });
         }
     }
 
     fn delete_documents(&mut self, documents: Vec<crate::DocId>) {
-        let connection = CONNECTION.lock().unwrap();
-        let ids = documents
-            .iter()
-            .map(|i| i.to_string())
-            .collect::<Vec<String>>()
-            .join(",");
-        connection
-            .prepare("DELETE FROM document_search WHERE doc_id IN (?);")
+        let connection = self.connection.lock().unwrap();
+        for table in [
+            "document_search",
+            "document_stems",
+            "document_facets",
+            "document_numbers",
+            "ranking",
+            "vectors",
+            "documents",
+        ] {
+            let mut stmt = connection
+                .prepare(&format!("DELETE FROM {table} WHERE doc_id = ?;"))
+                .unwrap();
+            for doc_id in &documents {
+                stmt.execute(params![doc_id]).unwrap();
+            }
+        }
+    }
+
+    fn word_docids(&self, word: &str) -> RoaringBitmap {
+        self.connection
+            .lock()
+            .unwrap()
+            .prepare("SELECT DISTINCT doc_id FROM document_search WHERE word = ?1;")
+            .unwrap()
+            .query_map(params![word], |row| row.get::<_, u32>(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    fn contains_word(&self, word: &str) -> bool {
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT 1 FROM document_search WHERE word = ?1 LIMIT 1;",
+                params![word],
+                |_| Ok(()),
+            )
+            .optional()
+            .unwrap()
+            .is_some()
+    }
+
+    fn words_starting_with(&self, prefix: &str) -> Vec<String> {
+        self.connection
+            .lock()
+            .unwrap()
+            .prepare(
+                "SELECT DISTINCT word FROM document_search WHERE substr(word, 1, ?2) = ?1 ORDER BY word;",
+            )
+            .unwrap()
+            .query_map(params![prefix, prefix.chars().count()], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    fn word_frequencies(&self) -> Vec<(String, u64)> {
+        self.connection
+            .lock()
+            .unwrap()
+            .prepare(
+                "SELECT word, COUNT(DISTINCT doc_id) FROM document_search GROUP BY word ORDER BY word;",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    fn stem_docids(&self, stem: &str) -> RoaringBitmap {
+        self.connection
+            .lock()
+            .unwrap()
+            .prepare("SELECT DISTINCT doc_id FROM document_stems WHERE stem = ?1;")
             .unwrap()
-            .execute(params![ids])
+            .query_map(params![stem], |row| row.get::<_, u32>(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    fn facet_docids(&self, field: &str, value: &str) -> RoaringBitmap {
+        self.connection
+            .lock()
+            .unwrap()
+            .prepare("SELECT DISTINCT doc_id FROM document_facets WHERE facet = ?1;")
+            .unwrap()
+            .query_map(params![facet_key(field, value)], |row| row.get::<_, u32>(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    fn number_postings(&self, field: &str, range: NumberRange) -> Vec<(f64, RoaringBitmap)> {
+        let (start, end) = key_range(field, range);
+        let bound = |bound, included, excluded| match bound {
+            Bound::Included(key) => (included, key),
+            Bound::Excluded(key) => (excluded, key),
+            Bound::Unbounded => unreachable!("The ranges of keys are always bounded"),
+        };
+        let ((start_op, start), (end_op, end)) = (bound(start, ">=", ">"), bound(end, "<=", "<"));
+
+        let connection = self.connection.lock().unwrap();
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT number, doc_id FROM document_numbers \
+                 WHERE number {start_op} ?1 AND number {end_op} ?2 ORDER BY number;"
+            ))
             .unwrap();
-        connection
-            .prepare("DELETE FORM documents WHERE doc_id IN (?);")
+        let rows = stmt
+            .query_map(params![start, end], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?))
+            })
+            .unwrap()
+            .map(Result::unwrap);
+
+        let mut postings: Vec<(String, RoaringBitmap)> = Vec::new();
+        for (key, docid) in rows {
+            match postings.last_mut() {
+                Some((last, docids)) if *last == key => {
+                    docids.insert(docid);
+                }
+                _ => postings.push((key, RoaringBitmap::from_iter([docid]))),
+            }
+        }
+        postings
+            .into_iter()
+            .map(|(key, docids)| (key_number(&key), docids))
+            .collect()
+    }
+
+    fn number_bounds(&self, field: &str) -> Option<(f64, f64)> {
+        let (start, end) = match key_range(field, (Bound::Unbounded, Bound::Unbounded)) {
+            (Bound::Included(start), Bound::Excluded(end)) => (start, end),
+            _ => unreachable!("The range of all the keys of an attribute is half open"),
+        };
+        let connection = self.connection.lock().unwrap();
+        let (min, max): (Option<String>, Option<String>) = connection
+            .query_row(
+                "SELECT MIN(number), MAX(number) FROM document_numbers \
+                 WHERE number >= ?1 AND number < ?2;",
+                params![start, end],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        Some((key_number(&min?), key_number(&max?)))
+    }
+
+    fn ranking_data(&self, docid: crate::DocId) -> RankingData {
+        self.connection
+            .lock()
             .unwrap()
-            .execute(params![ids])
+            .prepare("SELECT data FROM ranking WHERE doc_id = ?1;")
+            .unwrap()
+            .query_row(params![docid], |row| row.get::<_, Vec<u8>>(0))
+            .optional()
+            .unwrap()
+            .map(|ranking| serde_json::from_slice(&ranking).expect("Corrupted ranking"))
+            .unwrap_or_default()
+    }
+
+    fn ranking_data_batch(&self, docids: &RoaringBitmap) -> Vec<RankingData> {
+        let connection = self.connection.lock().unwrap();
+        let mut stmt = connection
+            .prepare("SELECT data FROM ranking WHERE doc_id = ?1;")
             .unwrap();
+        docids
+            .iter()
+            .map(|docid| {
+                stmt.query_row(params![docid], |row| row.get::<_, Vec<u8>>(0))
+                    .optional()
+                    .unwrap()
+                    .map(|ranking| serde_json::from_slice(&ranking).expect("Corrupted ranking"))
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    fn vectors(&self) -> Vec<(crate::DocId, Vec<f32>)> {
+        self.connection
+            .lock()
+            .unwrap()
+            .prepare("SELECT doc_id, vector FROM vectors;")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get::<_, u32>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })
+            .unwrap()
+            .map(|row| {
+                let (docid, vector) = row.unwrap();
+                (
+                    docid,
+                    serde_json::from_slice(&vector).expect("Corrupted database"),
+                )
+            })
+            .collect()
     }
 
-    fn search(&self, query: &crate::Query) -> Vec<DocId> {
-        let words = tokenize(query.q.as_deref().unwrap_or(""))
-            .collect::<Vec<String>>()
-            .join(",");
+    fn settings(&self) -> Settings {
+        self.connection
+            .lock()
+            .unwrap()
+            .prepare("SELECT settings FROM settings WHERE id = 0;")
+            .unwrap()
+            .query_row([], |row| row.get::<_, Vec<u8>>(0))
+            .optional()
+            .unwrap()
+            .map(|settings| serde_json::from_slice(&settings).expect("Corrupted settings"))
+            .unwrap_or_default()
+    }
 
-        let res = CONNECTION
+    fn put_settings(&mut self, settings: Settings) {
+        let settings = serde_json::to_vec(&settings).expect("Error while serializing settings");
+        self.connection
             .lock()
             .unwrap()
             .prepare(
                 r#"
-            SELECT DISTINCT document_search.doc_id FROM document_search
-            WHERE document_search.word IN (?);
+            INSERT INTO settings (id, settings) VALUES (0, ?)
+            ON CONFLICT(id) DO UPDATE SET settings = excluded.settings;
             "#,
             )
             .unwrap()
-            .query_map(params![words], |row| row.get::<_, u32>(0))
+            .execute(params![settings])
+            .expect("Error while updating settings");
+    }
+
+    fn curation_rules(&self) -> Rules {
+        self.connection
+            .lock()
             .unwrap()
-            .map(Result::unwrap)
-            .collect();
+            .prepare("SELECT rules FROM rules WHERE id = 0;")
+            .unwrap()
+            .query_row([], |row| row.get::<_, Vec<u8>>(0))
+            .optional()
+            .unwrap()
+            .map(|rules| serde_json::from_slice(&rules).expect("Corrupted rules"))
+            .unwrap_or_default()
+    }
 
-        res
+    fn put_curation_rules(&mut self, rules: Rules) {
+        let rules = serde_json::to_vec(&rules).expect("Error while serializing rules");
+        self.connection
+            .lock()
+            .unwrap()
+            .prepare(
+                r#"
+            INSERT INTO rules (id, rules) VALUES (0, ?)
+            ON CONFLICT(id) DO UPDATE SET rules = excluded.rules;
+            "#,
+            )
+            .unwrap()
+            .execute(params![rules])
+            .expect("Error while updating rules");
+    }
+
+    fn custom_tokenizer(&self) -> Option<Arc<dyn Tokenizer>> {
+        self.tokenizer.clone()
+    }
+
+    fn set_tokenizer(&mut self, tokenizer: Arc<dyn Tokenizer>) {
+        self.tokenizer = Some(tokenizer);
+    }
+
+    fn version(&self) -> u32 {
+        self.connection
+            .lock()
+            .unwrap()
+            .prepare("SELECT version FROM version WHERE id = 0;")
+            .unwrap()
+            .query_row([], |row| row.get::<_, u32>(0))
+            .optional()
+            .unwrap()
+            .unwrap_or_default()
+    }
+
+    fn put_version(&mut self, version: u32) {
+        self.connection
+            .lock()
+            .unwrap()
+            .prepare(
+                r#"
+            INSERT INTO version (id, version) VALUES (0, ?)
+            ON CONFLICT(id) DO UPDATE SET version = excluded.version;
+            "#,
+            )
+            .unwrap()
+            .execute(params![version])
+            .expect("Error while updating the version");
+    }
+
+    fn clear_postings(&mut self) {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute("DELETE FROM document_search;", [])
+            .unwrap();
+        connection
+            .execute("DELETE FROM document_stems;", [])
+            .unwrap();
+        connection
+            .execute("DELETE FROM document_facets;", [])
+            .unwrap();
+        connection
+            .execute("DELETE FROM document_numbers;", [])
+            .unwrap();
+        connection.execute("DELETE FROM ranking;", []).unwrap();
+        connection.execute("DELETE FROM vectors;", []).unwrap();
+    }
+
+    fn clear_documents(&mut self) {
+        let connection = self.connection.lock().unwrap();
+        connection.execute("DELETE FROM documents;", []).unwrap();
+    }
+
+    fn open(uid: &str) -> Self {
+        Self::open_path(&index_path(uid, DB_NAME))
+    }
+
+    fn uids() -> Vec<String> {
+        index_uids(DB_NAME)
     }
 
     fn clear_database() {
-        let connection = CONNECTION.lock().unwrap();
+        let connection = Connection::open(DB_NAME).expect("Couldn’t init sqlite database");
         match connection.execute("DELETE FROM document_search;", []) {
             Ok(nb_rows) => println!("document_search has been purged. {} rows deleted", nb_rows),
             Err(err) => println!("document_search couldn’t be deleted {}", err),
         }
+        match connection.execute("DELETE FROM document_stems;", []) {
+            Ok(nb_rows) => println!("document_stems has been purged. {} rows deleted", nb_rows),
+            Err(err) => println!("document_stems couldn’t be deleted {}", err),
+        }
+        match connection.execute("DELETE FROM document_facets;", []) {
+            Ok(nb_rows) => println!("document_facets has been purged. {} rows deleted", nb_rows),
+            Err(err) => println!("document_facets couldn’t be deleted {}", err),
+        }
+        match connection.execute("DELETE FROM document_numbers;", []) {
+            Ok(nb_rows) => println!("document_numbers has been purged. {} rows deleted", nb_rows),
+            Err(err) => println!("document_numbers couldn’t be deleted {}", err),
+        }
+        match connection.execute("DELETE FROM ranking;", []) {
+            Ok(nb_rows) => println!("ranking has been purged. {} rows deleted", nb_rows),
+            Err(err) => println!("ranking couldn’t be deleted {}", err),
+        }
+        match connection.execute("DELETE FROM vectors;", []) {
+            Ok(nb_rows) => println!("vectors has been purged. {} rows deleted", nb_rows),
+            Err(err) => println!("vectors couldn’t be deleted {}", err),
+        }
         match connection.execute("DELETE FROM documents;", []) {
             Ok(nb_rows) => println!("documents has been purged. {} rows deleted", nb_rows),
             Err(err) => println!("documents couldn’t be deleted {}", err),
@@ -146,7 +603,13 @@ impl Index for SQLite {
 
 impl Default for SQLite {
     fn default() -> Self {
-        let connection = CONNECTION.lock().unwrap();
+        Self::open_path(Path::new(DB_NAME))
+    }
+}
+
+impl SQLite {
+    fn open_path(path: &Path) -> Self {
+        let connection = Connection::open(path).expect("Couldn’t init sqlite database");
         connection
             .prepare(
                 r#"
@@ -172,6 +635,120 @@ impl Default for SQLite {
             .expect("Error while preparing init query")
             .raw_execute()
             .expect("Error while executing init query");
-        Self {}
+        connection
+            .prepare(
+                r#"
+            CREATE TABLE IF NOT EXISTS document_stems (
+                doc_id INT NOT NULL,
+                stem TEXT NOT NULL,
+                FOREIGN KEY(doc_id) REFERENCES documents(doc_id)
+            );
+        "#,
+            )
+            .expect("Error while preparing init query")
+            .raw_execute()
+            .expect("Error while executing init query");
+        connection
+            .prepare(
+                r#"
+            CREATE TABLE IF NOT EXISTS document_facets (
+                doc_id INT NOT NULL,
+                facet TEXT NOT NULL,
+                FOREIGN KEY(doc_id) REFERENCES documents(doc_id)
+            );
+        "#,
+            )
+            .expect("Error while preparing init query")
+            .raw_execute()
+            .expect("Error while executing init query");
+        connection
+            .prepare(
+                r#"
+            CREATE TABLE IF NOT EXISTS document_numbers (
+                doc_id INT NOT NULL,
+                number TEXT NOT NULL,
+                FOREIGN KEY(doc_id) REFERENCES documents(doc_id)
+            );
+        "#,
+            )
+            .expect("Error while preparing init query")
+            .raw_execute()
+            .expect("Error while executing init query");
+        // the ranges of numbers are ranges of this index
+        connection
+            .prepare(
+                r#"
+            CREATE INDEX IF NOT EXISTS document_numbers_number ON document_numbers (number);
+        "#,
+            )
+            .expect("Error while preparing init query")
+            .raw_execute()
+            .expect("Error while executing init query");
+        connection
+            .prepare(
+                r#"
+            CREATE TABLE IF NOT EXISTS version (
+                id INT PRIMARY KEY,
+                version INT NOT NULL
+            );
+        "#,
+            )
+            .expect("Error while preparing init query")
+            .raw_execute()
+            .expect("Error while executing init query");
+        connection
+            .prepare(
+                r#"
+            CREATE TABLE IF NOT EXISTS settings (
+                id INT PRIMARY KEY,
+                settings BLOB NOT NULL
+            );
+        "#,
+            )
+            .expect("Error while preparing init query")
+            .raw_execute()
+            .expect("Error while executing init query");
+        connection
+            .prepare(
+                r#"
+            CREATE TABLE IF NOT EXISTS rules (
+                id INT PRIMARY KEY,
+                rules BLOB NOT NULL
+            );
+        "#,
+            )
+            .expect("Error while preparing init query")
+            .raw_execute()
+            .expect("Error while executing init query");
+        connection
+            .prepare(
+                r#"
+            CREATE TABLE IF NOT EXISTS ranking (
+                doc_id INT PRIMARY KEY,
+                data BLOB NOT NULL,
+                FOREIGN KEY(doc_id) REFERENCES documents(doc_id)
+            );
+        "#,
+            )
+            .expect("Error while preparing init query")
+            .raw_execute()
+            .expect("Error while executing init query");
+        connection
+            .prepare(
+                r#"
+            CREATE TABLE IF NOT EXISTS vectors (
+                doc_id INT PRIMARY KEY,
+                vector BLOB NOT NULL,
+                FOREIGN KEY(doc_id) REFERENCES documents(doc_id)
+            );
+        "#,
+            )
+            .expect("Error while preparing init query")
+            .raw_execute()
+            .expect("Error while executing init query");
+        Self {
+            connection: Mutex::new(connection),
+            tokenizer: None,
+        }
     }
 }
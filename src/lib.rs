@@ -1,11 +1,26 @@
 mod api;
+mod curation;
+mod filter;
+mod formats;
+mod geo;
+mod numbers;
+mod search;
+mod settings;
+mod suggest;
 mod tokenizer;
+mod vector;
 
-pub use api::{run, Document, Query};
-pub use tokenizer::tokenize;
+pub use api::{run, Document, MinimumShouldMatch, Query};
+pub use curation::{Matching, Rule, Rules};
+pub use geo::Point;
+pub use search::{Bucket, Hit, ScoreDetails, SearchError};
+pub use settings::{CustomRule, Order, Settings};
+pub use tokenizer::{
+    DefaultTokenizer, Language, SeparatorRule, Separators, Token, TokenKind, Tokenizer,
+};
 
 pub mod indexes;
 
-pub use crate::indexes::Index;
+pub use crate::indexes::{Index, RankingData};
 
 type DocId = u32;
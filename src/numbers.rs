@@ -0,0 +1,255 @@
+//! The numbers of the faceted attributes, and their ISO-8601 dates converted to unix timestamps,
+//! are indexed under keys sorted like the numbers, so a range of numbers is a range of keys.
+
+use std::ops::Bound;
+
+use serde_json::Value;
+
+/// A range of numbers, like `(Bound::Excluded(1990.0), Bound::Unbounded)` for `> 1990`.
+pub type NumberRange = (Bound<f64>, Bound<f64>);
+
+/// The key of the postings of a number of an attribute, the number is written in hexadecimal
+/// after flipping its bits so the keys of an attribute are sorted by number.
+pub fn number_key(field: &str, number: f64) -> String {
+    // `-0.0` and `0.0` are the same number
+    let bits = (number + 0.0).to_bits();
+    let ordered = if bits >> 63 == 0 {
+        bits | 1 << 63
+    } else {
+        !bits
+    };
+    format!("{field}\0{ordered:016x}")
+}
+
+/// The number stored in a key built by `number_key`.
+pub fn key_number(key: &str) -> f64 {
+    let (_, ordered) = key.rsplit_once('\0').expect("Corrupted database");
+    let ordered = u64::from_str_radix(ordered, 16).expect("Corrupted database");
+    let bits = if ordered >> 63 == 1 {
+        ordered & !(1 << 63)
+    } else {
+        !ordered
+    };
+    f64::from_bits(bits)
+}
+
+/// The range of the keys of the numbers of an attribute in a range, an empty range of numbers
+/// gives an empty range of keys that can still be looked for.
+pub fn key_range(field: &str, range: NumberRange) -> (Bound<String>, Bound<String>) {
+    if !is_valid(&range) {
+        let key = format!("{field}\0");
+        return (Bound::Included(key.clone()), Bound::Excluded(key));
+    }
+    let start = match range.0 {
+        Bound::Included(number) => Bound::Included(number_key(field, number)),
+        Bound::Excluded(number) => Bound::Excluded(number_key(field, number)),
+        Bound::Unbounded => Bound::Included(format!("{field}\0")),
+    };
+    let end = match range.1 {
+        Bound::Included(number) => Bound::Included(number_key(field, number)),
+        Bound::Excluded(number) => Bound::Excluded(number_key(field, number)),
+        // the char following the separator comes after all the numbers of the attribute
+        Bound::Unbounded => Bound::Excluded(format!("{field}\u{1}")),
+    };
+    (start, end)
+}
+
+/// Whether the start of a range is before its end.
+fn is_valid(range: &NumberRange) -> bool {
+    match range {
+        (Bound::Included(start), Bound::Included(end)) => start <= end,
+        (Bound::Included(start) | Bound::Excluded(start), Bound::Excluded(end))
+        | (Bound::Excluded(start), Bound::Included(end)) => start < end,
+        _ => true,
+    }
+}
+
+/// The numbers of an attribute: its numbers and its dates, the arrays are flattened.
+pub fn number_values(value: &Value) -> Vec<f64> {
+    match value {
+        Value::Number(n) => n.as_f64().into_iter().collect(),
+        Value::String(s) => parse_date(s).into_iter().collect(),
+        Value::Array(values) => values.iter().flat_map(number_values).collect(),
+        Value::Null | Value::Bool(_) | Value::Object(_) => Vec::new(),
+    }
+}
+
+/// A number, or an ISO-8601 date converted to a unix timestamp.
+pub fn parse_number(s: &str) -> Option<f64> {
+    match s.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => Some(number),
+        _ => parse_date(s),
+    }
+}
+
+/// Parse an ISO-8601 date, like `2022-08-01`, `2022-08-01T12:30:00Z` or
+/// `2022-08-01T12:30:00.5+02:00`, into a unix timestamp in seconds. The dates without
+/// an offset are in UTC.
+pub fn parse_date(date: &str) -> Option<f64> {
+    let date = date.trim();
+    let (date, time) = match date.split_once(['T', 't', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (date, None),
+    };
+
+    let mut parts = date.split('-');
+    let year = digits(parts.next()?, 4)?;
+    let month = digits(parts.next()?, 2)?;
+    let day = digits(parts.next()?, 2)?;
+    if parts.next().is_some() || !(1..=12).contains(&month) {
+        return None;
+    }
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if !(1..=days_in_month).contains(&day) {
+        return None;
+    }
+
+    let seconds = match time {
+        Some(time) => parse_time(time)?,
+        None => 0.0,
+    };
+    Some(days_from_civil(year, month, day) as f64 * 86_400.0 + seconds)
+}
+
+/// Parse `HH:MM[:SS[.fraction]]` followed by `Z` or an offset, into seconds since midnight UTC.
+fn parse_time(time: &str) -> Option<f64> {
+    let (time, offset) = if let Some(time) = time.strip_suffix(['Z', 'z']) {
+        (time, 0)
+    } else if let Some(position) = time.rfind(['+', '-']) {
+        let (time, offset) = time.split_at(position);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let offset = offset[1..].replace(':', "");
+        let (hours, minutes) = match offset.len() {
+            2 => (digits(&offset, 2)?, 0),
+            4 => (digits(&offset[..2], 2)?, digits(&offset[2..], 2)?),
+            _ => return None,
+        };
+        (time, sign * (hours * 3600 + minutes * 60))
+    } else {
+        (time, 0)
+    };
+
+    let mut parts = time.split(':');
+    let hours = digits(parts.next()?, 2)?;
+    let minutes = digits(parts.next()?, 2)?;
+    let seconds = match parts.next() {
+        Some(seconds) => {
+            let (whole, fraction) = seconds.split_once('.').unwrap_or((seconds, "0"));
+            if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            digits(whole, 2)? as f64 + format!("0.{fraction}").parse::<f64>().ok()?
+        }
+        None => 0.0,
+    };
+    if parts.next().is_some() || hours > 23 || minutes > 59 || seconds >= 61.0 {
+        return None;
+    }
+
+    Some((hours * 3600 + minutes * 60 - offset) as f64 + seconds)
+}
+
+/// Parse exactly `len` ascii digits.
+fn digits(s: &str, len: usize) -> Option<i64> {
+    if s.len() == len && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// The number of days since 1970-01-01 of a date of the proleptic gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // the years start in march so the leap day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{key_number, number_key, parse_date};
+
+    #[test]
+    fn keys_sorted_like_numbers() {
+        let numbers = [
+            f64::NEG_INFINITY,
+            -1e300,
+            -1984.5,
+            -1.0,
+            -f64::MIN_POSITIVE,
+            0.0,
+            f64::MIN_POSITIVE,
+            0.5,
+            1.0,
+            1984.0,
+            1e300,
+            f64::INFINITY,
+        ];
+        let keys: Vec<_> = numbers.iter().map(|n| number_key("year", *n)).collect();
+        assert!(keys.windows(2).all(|keys| keys[0] < keys[1]));
+        for (n, key) in numbers.iter().zip(&keys) {
+            assert_eq!(key_number(key), *n);
+        }
+    }
+
+    #[test]
+    fn negative_zero_is_zero() {
+        assert_eq!(number_key("year", -0.0), number_key("year", 0.0));
+        assert_eq!(key_number(&number_key("year", -0.0)), 0.0);
+    }
+
+    #[test]
+    fn keys_of_an_attribute_only() {
+        // the key of the greatest number of an attribute is still before the next attribute
+        assert!(number_key("year", f64::INFINITY).as_str() < "year\u{1}");
+        assert!(number_key("year", f64::NEG_INFINITY).as_str() > "year\0");
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date("1970-01-01"), Some(0.0));
+        assert_eq!(parse_date("2022-08-01"), Some(1_659_312_000.0));
+        assert_eq!(parse_date("2022-08-01T12:30:00Z"), Some(1_659_357_000.0));
+        assert_eq!(
+            parse_date("2022-08-01T12:30:00+02:00"),
+            Some(1_659_349_800.0)
+        );
+        assert_eq!(
+            parse_date("2022-08-01T12:30:00-0130"),
+            Some(1_659_362_400.0)
+        );
+        assert_eq!(parse_date("2022-08-01 12:30:00.5"), Some(1_659_357_000.5));
+        assert_eq!(parse_date("1969-12-31T23:59:59Z"), Some(-1.0));
+        assert_eq!(parse_date("2000-02-29"), Some(951_782_400.0));
+    }
+
+    #[test]
+    fn invalid_dates() {
+        for date in [
+            "",
+            "2022",
+            "2022-8-1",
+            "2022-13-01",
+            "2022-02-29",
+            "1900-02-29",
+            "2022-04-31",
+            "2022-08-01T24:00",
+            "2022-08-01T12:60",
+            "2022-08-01T12:30:00.",
+            "2022-08-01T12:30:00+2",
+            "2022-08-01-01",
+        ] {
+            assert_eq!(parse_date(date), None, "{date}");
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::{Document, Index, Token, Tokenizer};
+
+use super::{
+    ranking::{Ranked, Scale},
+    QueryWord,
+};
+
+/// The distance between two words from which they are considered unrelated.
+const MAX_DISTANCE: usize = 8;
+/// The parameters of BM25, the usual ones.
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+/// Why a document ranks where it does. The rules with an `order` are applied in this order,
+/// the proximity and BM25 are only given as information and don't change the order.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreDetails {
+    pub words: Words,
+    pub exactness: Exactness,
+    pub attribute: Attribute,
+    /// The geo sort and the custom ranking rules, by rule.
+    #[serde(flatten)]
+    pub custom: BTreeMap<String, Custom>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proximity: Option<Proximity>,
+    pub bm25: Bm25,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Words {
+    pub order: usize,
+    pub matching_words: usize,
+    pub max_matching_words: usize,
+    pub score: f64,
+}
+
+/// minisearch doesn't tolerate typos, this rule measures how far the matched words are
+/// from the query: accents, stems, compound words and synonyms.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Exactness {
+    pub order: usize,
+    pub cost: u32,
+    pub max_cost: u32,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attribute {
+    pub order: usize,
+    /// The sum of the boosts of the attributes matching each word.
+    pub weight: f64,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Custom {
+    pub order: usize,
+    /// The value of the attribute, or the distance in meters for the geo sort.
+    pub value: Option<f64>,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Proximity {
+    /// The sum of the distances between the consecutive words of the query.
+    pub distance: usize,
+    pub max_distance: usize,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bm25 {
+    pub score: f64,
+}
+
+/// Explain the score of some documents, they must all be in the index.
+pub(super) fn explain<'a, I: Index>(
+    index: &I,
+    tokenizer: &dyn Tokenizer,
+    query: &[QueryWord],
+    scale: &Scale,
+    ranked: impl Iterator<Item = &'a Ranked>,
+) -> Vec<ScoreDetails> {
+    let ranked: Vec<_> = ranked.collect();
+    let documents: Vec<_> = ranked
+        .iter()
+        .map(|ranked| {
+            let document = index
+                .get_document(ranked.docid)
+                .expect("Corrupted database");
+            positions(tokenizer, &document)
+        })
+        .collect();
+
+    // the patterns don't have a single word to look for
+    let words: Vec<_> = query
+        .iter()
+        .map(|word| word.token().map(|token| token.normalized.as_str()))
+        .collect();
+    // the average length is only computed on the documents explained, computing it on
+    // the whole index would require reading all the documents
+    let average_len =
+        documents.iter().map(Vec::len).sum::<usize>() as f64 / documents.len().max(1) as f64;
+    let nb_documents = index.nb_documents() as f64;
+    let idfs: HashMap<&str, f64> = words
+        .iter()
+        .flatten()
+        .map(|word| {
+            let frequency = index.word_docids(word).len() as f64;
+            let idf = (1.0 + (nb_documents - frequency + 0.5) / (frequency + 0.5)).ln();
+            (*word, idf)
+        })
+        .collect();
+
+    ranked
+        .into_iter()
+        .zip(documents)
+        .map(|(ranked, document)| {
+            let rules = scale.rules(ranked);
+            let mut custom = BTreeMap::new();
+            // the geo sort is applied right after the words
+            let sorted = scale.sort().is_some() as usize;
+            if let Some(sort) = scale.sort() {
+                custom.insert(
+                    sort.to_string(),
+                    Custom {
+                        order: 1,
+                        value: ranked.distance,
+                        score: rules[1].score(),
+                    },
+                );
+            }
+            for (order, (rule, value)) in scale
+                .custom_ranking()
+                .iter()
+                .zip(&ranked.values)
+                .enumerate()
+            {
+                let order = 3 + sorted + order;
+                custom.insert(
+                    rule.to_string(),
+                    Custom {
+                        order,
+                        value: *value,
+                        score: rules[order].score(),
+                    },
+                );
+            }
+
+            ScoreDetails {
+                words: Words {
+                    order: 0,
+                    matching_words: ranked.matched,
+                    max_matching_words: words.len(),
+                    score: rules[0].score(),
+                },
+                exactness: Exactness {
+                    order: 1 + sorted,
+                    cost: ranked.cost,
+                    max_cost: rules[1 + sorted].max_rank,
+                    score: rules[1 + sorted].score(),
+                },
+                attribute: Attribute {
+                    order: 2 + sorted,
+                    weight: ranked.weight,
+                    score: rules[2 + sorted].score(),
+                },
+                custom,
+                proximity: proximity(&words, &document),
+                bm25: Bm25 {
+                    score: bm25(&idfs, &document, average_len),
+                },
+            }
+        })
+        .collect()
+}
+
+/// The words of a document along with their position, the fields are
+/// `MAX_DISTANCE` words apart so their words are never related.
+fn positions(tokenizer: &dyn Tokenizer, document: &Document) -> Vec<(String, usize)> {
+    let mut positions = Vec::new();
+    let mut offset = 0;
+    for field in document.fields() {
+        let mut last = offset;
+        for token in tokenizer.tokenize(field).filter(Token::is_word) {
+            last = offset + token.position;
+            positions.push((token.normalized, last));
+        }
+        offset = last + MAX_DISTANCE;
+    }
+    positions
+}
+
+/// The distance between the consecutive words of the query in the document,
+/// there is nothing to measure with less than two words.
+fn proximity(words: &[Option<&str>], document: &[(String, usize)]) -> Option<Proximity> {
+    let mut pairs = 0;
+    let mut distance = 0;
+
+    for window in words.windows(2) {
+        let (left, right) = match window {
+            [Some(left), Some(right)] => (left, right),
+            _ => continue,
+        };
+        let occurrences = |word: &str| -> Vec<usize> {
+            document
+                .iter()
+                .filter(|(other, _)| other == word)
+                .map(|(_, position)| *position)
+                .collect()
+        };
+        let (lefts, rights) = (occurrences(left), occurrences(right));
+        let closest = lefts
+            .iter()
+            .flat_map(|left| rights.iter().map(move |right| left.abs_diff(*right)))
+            .filter(|distance| *distance > 0)
+            .min()
+            .unwrap_or(MAX_DISTANCE);
+
+        pairs += 1;
+        distance += closest.min(MAX_DISTANCE);
+    }
+
+    if pairs == 0 {
+        return None;
+    }
+    // two words next to each other are at a distance of 1
+    let max_distance = pairs * MAX_DISTANCE;
+    Some(Proximity {
+        distance,
+        max_distance,
+        score: (max_distance - distance) as f64 / (max_distance - pairs) as f64,
+    })
+}
+
+/// The BM25 of the words of the query in the document.
+fn bm25(idfs: &HashMap<&str, f64>, document: &[(String, usize)], average_len: f64) -> f64 {
+    let len = document.len() as f64;
+    idfs.iter()
+        .map(|(word, idf)| {
+            let frequency = document.iter().filter(|(other, _)| other == word).count() as f64;
+            idf * frequency * (BM25_K1 + 1.0)
+                / (frequency + BM25_K1 * (1.0 - BM25_B + BM25_B * len / average_len.max(1.0)))
+        })
+        .sum()
+}
@@ -0,0 +1,78 @@
+use std::{collections::BTreeMap, ops::Bound};
+
+use roaring::RoaringBitmap;
+use serde::Serialize;
+
+use crate::{Hit, Index, Settings};
+
+use super::SearchError;
+
+/// The number of documents with a number between `from`, included, and `to`, excluded.
+#[derive(Debug, Clone, Serialize)]
+pub struct Bucket {
+    pub from: f64,
+    pub to: f64,
+    pub count: u64,
+}
+
+/// Count the hits in buckets of the faceted attributes, like `year:10,price:100`.
+/// The empty buckets are skipped and a document is only counted once per bucket.
+pub fn histograms<I: Index>(
+    index: &I,
+    settings: &Settings,
+    histograms: &str,
+    hits: &[Hit],
+) -> Result<BTreeMap<String, Vec<Bucket>>, SearchError> {
+    let hits: RoaringBitmap = hits.iter().map(|hit| hit.docid).collect();
+    let faceted = settings.faceted();
+
+    let mut result = BTreeMap::new();
+    for histogram in histograms.split(',') {
+        let invalid = || {
+            SearchError::InvalidHistogram(format!(
+                "Invalid histogram `{histogram}`, expected `attribute:interval` like `year:10`."
+            ))
+        };
+        let (field, interval) = histogram.trim().rsplit_once(':').ok_or_else(invalid)?;
+        let interval: f64 = interval.trim().parse().map_err(|_| invalid())?;
+        if !interval.is_finite() || interval <= 0.0 {
+            return Err(invalid());
+        }
+        if !faceted.contains(field) {
+            return Err(SearchError::NotFaceted {
+                attribute: field.to_string(),
+            });
+        }
+
+        let mut buckets: Vec<(Bucket, RoaringBitmap)> = Vec::new();
+        for (number, docids) in index.number_postings(field, (Bound::Unbounded, Bound::Unbounded)) {
+            let docids = docids & &hits;
+            if docids.is_empty() {
+                continue;
+            }
+            let from = (number / interval).floor() * interval;
+            match buckets.last_mut() {
+                Some((bucket, bucket_docids)) if bucket.from == from => *bucket_docids |= docids,
+                _ => buckets.push((
+                    Bucket {
+                        from,
+                        to: from + interval,
+                        count: 0,
+                    },
+                    docids,
+                )),
+            }
+        }
+
+        let buckets = buckets
+            .into_iter()
+            .map(|(bucket, docids)| Bucket {
+                count: docids.len(),
+                ..bucket
+            })
+            .collect();
+        result.insert(field.to_string(), buckets);
+    }
+
+    Ok(result)
+}
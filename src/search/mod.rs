@@ -0,0 +1,518 @@
+mod details;
+mod histogram;
+mod pattern;
+mod ranking;
+mod similar;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::Range,
+};
+
+use roaring::RoaringBitmap;
+
+use crate::{
+    curation::Curation, filter::Filter, geo::GeoSort, vector, DocId, Index, Query, Settings, Token,
+    Tokenizer,
+};
+
+pub use details::ScoreDetails;
+pub use histogram::{histograms, Bucket};
+use pattern::Pattern;
+use ranking::Scale;
+pub use similar::similar;
+
+/// Cost of a term matching exactly what the user typed.
+const EXACT: u8 = 0;
+/// Cost of a term matching what the user typed without its accents.
+const UNACCENTED: u8 = 1;
+/// Cost of a term matching a word sharing the same stem as what the user typed.
+const STEM: u8 = 2;
+/// Cost of a term matching the concatenation of some words of the query, or a
+/// word of the query split in multiple words.
+const COMPOUND: u8 = 3;
+/// Cost of a term matching one of the synonyms of what the user typed.
+const SYNONYM: u8 = 4;
+
+/// The maximum number of adjacent words of the query concatenated together.
+const MAX_CONCATENATED_WORDS: usize = 3;
+/// The shortest part of a word split in two, in chars.
+const MIN_SPLIT_LEN: usize = 2;
+/// The weight of the vector search in an hybrid search, the keyword search weighs the rest.
+const DEFAULT_SEMANTIC_RATIO: f64 = 0.5;
+
+#[derive(Debug)]
+pub enum SearchError {
+    /// A regex of the query can't be parsed.
+    InvalidPattern { pattern: String, error: String },
+    /// A wildcard or a regex matches more words than allowed by the settings.
+    TooManyExpansions { pattern: String, limit: usize },
+    /// The attribute is not indexed by value.
+    NotFaceted { attribute: String },
+    /// The filter can't be parsed.
+    InvalidFilter(String),
+    /// The sort can't be parsed.
+    InvalidSort(String),
+    /// The histogram can't be parsed.
+    InvalidHistogram(String),
+    /// The vector of the query doesn't match the dimensions of the settings.
+    InvalidVector(String),
+    /// The semantic ratio isn't between 0 and 1.
+    InvalidSemanticRatio(f64),
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchError::InvalidPattern { pattern, error } => {
+                write!(f, "Invalid pattern `{pattern}`: {error}")
+            }
+            SearchError::TooManyExpansions { pattern, limit } => write!(
+                f,
+                "The pattern `{pattern}` matches more than {limit} words, \
+                 the limit can be raised with the `maxExpansions` setting."
+            ),
+            SearchError::NotFaceted { attribute } => write!(
+                f,
+                "The attribute `{attribute}` is not faceted, \
+                 it must be added to the `facetedAttributes` setting."
+            ),
+            SearchError::InvalidFilter(error)
+            | SearchError::InvalidSort(error)
+            | SearchError::InvalidHistogram(error)
+            | SearchError::InvalidVector(error) => write!(f, "{error}"),
+            SearchError::InvalidSemanticRatio(ratio) => write!(
+                f,
+                "Invalid `semanticRatio` {ratio}, it must be between 0 and 1."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+/// A document matching a query.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub docid: DocId,
+    /// How relevant the document is, between 0 and 1.
+    pub score: f64,
+    /// Only computed for the documents returned when the query asks for it.
+    pub details: Option<ScoreDetails>,
+    /// The document was pinned by a curation rule.
+    pub pinned: bool,
+    /// The distance to the point of the geo sort in meters, if the document has a `_geo`.
+    pub geo_distance: Option<f64>,
+    /// The similarity of the document to the vector of the query, between 0 and 1.
+    pub semantic_score: Option<f64>,
+}
+
+/// A word of the query.
+enum QueryWord {
+    Token(Token),
+    Pattern(Box<Pattern>),
+}
+
+impl QueryWord {
+    fn token(&self) -> Option<&Token> {
+        match self {
+            QueryWord::Token(token) => Some(token),
+            QueryWord::Pattern(_) => None,
+        }
+    }
+}
+
+/// One way of matching some consecutive words of the query.
+struct Term {
+    /// Positions of the query words matched by this term.
+    span: Range<usize>,
+    docids: RoaringBitmap,
+    /// The lower the cost, the closer the term is to what the user typed.
+    cost: u8,
+    /// The words, or stems, a document must contain to match this term.
+    words: Vec<String>,
+}
+
+/// Execute a query against any index, only the postings of the words are needed.
+/// All the documents matching the query are returned, the details are only computed
+/// for the page of documents asked by the query.
+pub fn execute<I: Index>(index: &I, query: &Query) -> Result<Vec<Hit>, SearchError> {
+    let settings = index.settings();
+    let tokenizer = index.tokenizer(&settings);
+    let q = query.q.as_deref().unwrap_or("");
+    let distinct = query.distinct.as_ref().or(settings.distinct.as_ref());
+    if let Some(attribute) = distinct {
+        if !settings.faceted().contains(attribute.as_str()) {
+            return Err(SearchError::NotFaceted {
+                attribute: attribute.clone(),
+            });
+        }
+    }
+    let filter = query
+        .filter
+        .as_deref()
+        .map(Filter::parse)
+        .transpose()
+        .map_err(SearchError::InvalidFilter)?;
+    let sort = query
+        .sort
+        .as_deref()
+        .map(GeoSort::parse)
+        .transpose()
+        .map_err(SearchError::InvalidSort)?;
+    if let Some(vector) = &query.vector {
+        match settings.vector_dimensions {
+            Some(dimensions) if dimensions == vector.len() => (),
+            Some(dimensions) => {
+                return Err(SearchError::InvalidVector(format!(
+                    "The vector has {} dimensions instead of {dimensions}.",
+                    vector.len()
+                )))
+            }
+            None => {
+                return Err(SearchError::InvalidVector(String::from(
+                    "The `vectorDimensions` setting must be set to search with a vector.",
+                )))
+            }
+        }
+    }
+    let semantic_ratio = query.semantic_ratio.unwrap_or(DEFAULT_SEMANTIC_RATIO);
+    if !(0.0..=1.0).contains(&semantic_ratio) {
+        return Err(SearchError::InvalidSemanticRatio(semantic_ratio));
+    }
+    let words = parse(q, &settings, &*tokenizer)?;
+    let terms = plan(index, &settings, &*tokenizer, &words)?;
+    let filter = filter.map(|filter| filter.docids(index));
+    let mut ranked = ranking::rank(
+        index,
+        &settings,
+        &terms,
+        words.len(),
+        filter.as_ref(),
+        sort.as_ref(),
+    );
+    if let Some(minimum) = query.minimum_should_match {
+        let required = minimum.required(words.len());
+        ranked.retain(|ranked| ranked.matched >= required);
+    }
+
+    let scale = Scale::new(index, &settings, sort.as_ref(), words.len());
+    let mut hits: Vec<_> = ranked
+        .iter()
+        .map(|ranked| Hit {
+            docid: ranked.docid,
+            score: scale.score(ranked),
+            details: None,
+            pinned: false,
+            geo_distance: ranked.distance,
+            semantic_score: None,
+        })
+        .collect();
+    if let Some(vector) = &query.vector {
+        // without any word there is nothing to mix with the vector search
+        let ratio = if words.is_empty() {
+            1.0
+        } else {
+            semantic_ratio
+        };
+        if ratio > 0.0 {
+            let similarities = vector::similarities(index, vector, filter.as_ref());
+            hits = hybrid(hits, similarities, ratio, query.offset + query.limit);
+        }
+    }
+    hits.retain(|hit| {
+        query
+            .ranking_score_threshold
+            .is_none_or(|threshold| hit.score >= threshold)
+    });
+    let curation = Curation::new(&index.curation_rules(), q, &*tokenizer);
+    let mut hits = curation.apply(index, hits, filter.as_ref(), query.ranking_score_threshold);
+    if let Some(attribute) = distinct {
+        deduplicate(index, attribute, &mut hits);
+    }
+
+    if query.show_ranking_score_details {
+        // the documents pinned without matching the query have nothing to explain
+        let ranked: HashMap<_, _> = ranked.iter().map(|ranked| (ranked.docid, ranked)).collect();
+        let page: Vec<_> = hits
+            .iter()
+            .skip(query.offset)
+            .take(query.limit)
+            .filter_map(|hit| ranked.get(&hit.docid).copied())
+            .collect();
+        let details = details::explain(index, &*tokenizer, &words, &scale, page.iter().copied());
+        let mut details: HashMap<_, _> = page
+            .iter()
+            .map(|ranked| ranked.docid)
+            .zip(details)
+            .collect();
+        for hit in hits.iter_mut().skip(query.offset).take(query.limit) {
+            hit.details = details.remove(&hit.docid);
+        }
+    }
+
+    Ok(hits)
+}
+
+/// Mix the hits of the keyword search with the documents closest to the vector of the query,
+/// by the weighted sum of their scores. A document missing from one of the searches gets 0 for
+/// it. Every hit of the keyword search gets its similarity, only the `k` closest documents it
+/// didn't find are added, and with a ratio of 1 only the `k` closest documents are kept.
+fn hybrid(keyword: Vec<Hit>, mut semantic: Vec<(DocId, f64)>, ratio: f64, k: usize) -> Vec<Hit> {
+    if ratio >= 1.0 {
+        semantic.truncate(k);
+    }
+    let similarities: HashMap<_, _> = semantic.iter().copied().collect();
+    let keyword: Vec<_> = keyword
+        .into_iter()
+        .filter(|hit| ratio < 1.0 || similarities.contains_key(&hit.docid))
+        .collect();
+    let found: HashSet<_> = keyword.iter().map(|hit| hit.docid).collect();
+
+    let mut hits: Vec<_> = keyword
+        .into_iter()
+        .map(|hit| {
+            let similarity = similarities.get(&hit.docid).copied();
+            Hit {
+                score: (1.0 - ratio) * hit.score + ratio * similarity.unwrap_or(0.0),
+                semantic_score: similarity,
+                ..hit
+            }
+        })
+        .collect();
+    hits.extend(
+        semantic
+            .into_iter()
+            .filter(|(docid, _)| !found.contains(docid))
+            .take(k)
+            .map(|(docid, similarity)| Hit {
+                docid,
+                score: ratio * similarity,
+                details: None,
+                pinned: false,
+                geo_distance: None,
+                semantic_score: Some(similarity),
+            }),
+    );
+    // the sort is stable, the documents scoring the same stay in the order of their search
+    hits.sort_by(|left, right| right.score.total_cmp(&left.score));
+    hits
+}
+
+/// Only keep the best hit for each value of a faceted attribute, the documents without
+/// a value are all kept. The other documents sharing a value are found with the facets.
+/// The pinned hits are always kept and come first, so they hide the other hits sharing
+/// their value. All the hits are deduplicated so the number of hits doesn't count the
+/// duplicates.
+fn deduplicate<I: Index>(index: &I, attribute: &str, hits: &mut Vec<Hit>) {
+    let docids: RoaringBitmap = hits.iter().map(|hit| hit.docid).collect();
+    let mut data: HashMap<_, _> = docids
+        .iter()
+        .zip(index.ranking_data_batch(&docids))
+        .collect();
+    let mut excluded = RoaringBitmap::new();
+    hits.retain(|hit| {
+        if excluded.contains(hit.docid) && !hit.pinned {
+            return false;
+        }
+        let values = data
+            .remove(&hit.docid)
+            .and_then(|mut data| data.facets.remove(attribute));
+        for value in values.into_iter().flatten() {
+            excluded |= index.facet_docids(attribute, &value);
+        }
+        true
+    });
+}
+
+/// Split the query into words, the parts of the query containing a `*` or a `?`
+/// are wildcards and, if the settings allow it, the parts surrounded by `/` are regexes.
+fn parse(
+    query: &str,
+    settings: &Settings,
+    tokenizer: &dyn Tokenizer,
+) -> Result<Vec<QueryWord>, SearchError> {
+    let mut words = Vec::new();
+
+    for part in query.split_whitespace() {
+        match part
+            .strip_prefix('/')
+            .and_then(|part| part.strip_suffix('/'))
+        {
+            Some(regex) if settings.allow_regex && !regex.is_empty() => {
+                words.push(QueryWord::Pattern(Box::new(Pattern::regex(regex)?)))
+            }
+            _ if part.contains(['*', '?']) => words.push(QueryWord::Pattern(Box::new(
+                Pattern::wildcard(part, tokenizer)?,
+            ))),
+            _ => words.extend(
+                tokenizer
+                    .tokenize(part)
+                    .filter(Token::is_word)
+                    .map(QueryWord::Token),
+            ),
+        }
+    }
+
+    Ok(words)
+}
+
+/// Expand the words of the query into every term that could match them.
+fn plan<I: Index>(
+    index: &I,
+    settings: &Settings,
+    tokenizer: &dyn Tokenizer,
+    query: &[QueryWord],
+) -> Result<Vec<Term>, SearchError> {
+    // the patterns can only match by themselves
+    let words: Vec<_> = query
+        .iter()
+        .map(|word| word.token().map(|token| token.normalized.as_str()))
+        .collect();
+    let tokens = || {
+        query
+            .iter()
+            .enumerate()
+            .filter_map(|(position, word)| Some((position, word.token()?)))
+    };
+    let mut terms = Vec::new();
+
+    for (position, word) in query.iter().enumerate() {
+        let token = match word {
+            QueryWord::Token(token) => token,
+            QueryWord::Pattern(pattern) => {
+                for word in pattern.expand(index, settings.max_expansions)? {
+                    terms.push(Term {
+                        span: position..position + 1,
+                        docids: index.word_docids(&word),
+                        cost: EXACT,
+                        words: vec![word],
+                    });
+                }
+                continue;
+            }
+        };
+        // when the user typed some accents the words without them are a bit further
+        let cost = match &token.accented {
+            Some(accented) => {
+                terms.push(Term {
+                    span: position..position + 1,
+                    docids: index.word_docids(accented),
+                    cost: EXACT,
+                    words: vec![accented.clone()],
+                });
+                UNACCENTED
+            }
+            None => EXACT,
+        };
+        terms.push(Term {
+            span: position..position + 1,
+            docids: index.word_docids(&token.normalized),
+            cost,
+            words: vec![token.normalized.clone()],
+        });
+    }
+
+    if let Some(language) = settings.language {
+        for (position, token) in tokens() {
+            let stem = language.stem(&token.normalized);
+            terms.push(Term {
+                span: position..position + 1,
+                docids: index.stem_docids(&stem),
+                cost: STEM,
+                words: vec![stem],
+            });
+        }
+    }
+
+    // `star wars` matches `starwars`
+    for len in 2..=MAX_CONCATENATED_WORDS {
+        for (position, window) in words.windows(len).enumerate() {
+            let concatenated = match window.iter().copied().collect::<Option<Vec<_>>>() {
+                Some(window) => window.concat(),
+                None => continue,
+            };
+            if index.contains_word(&concatenated) {
+                terms.push(Term {
+                    span: position..position + len,
+                    docids: index.word_docids(&concatenated),
+                    cost: COMPOUND,
+                    words: vec![concatenated],
+                });
+            }
+        }
+    }
+    // `starwars` matches `star wars`
+    for (position, token) in tokens() {
+        for (left, right) in splits(&token.normalized) {
+            if index.contains_word(left) && index.contains_word(right) {
+                terms.push(Term {
+                    span: position..position + 1,
+                    docids: index.word_docids(left) & index.word_docids(right),
+                    cost: COMPOUND,
+                    words: vec![left.to_string(), right.to_string()],
+                });
+            }
+        }
+    }
+    // `getUserName` matches `get user name`
+    for (position, token) in tokens() {
+        if let Some(docids) = token
+            .sub_words
+            .iter()
+            .map(|sub_word| index.word_docids(sub_word))
+            .reduce(|acc, bitmap| acc & bitmap)
+        {
+            terms.push(Term {
+                span: position..position + 1,
+                docids,
+                cost: COMPOUND,
+                words: token.sub_words.clone(),
+            });
+        }
+    }
+
+    for (from, synonyms) in settings.synonyms.iter() {
+        let from: Vec<_> = tokenizer.words(from).collect();
+        if from.is_empty() {
+            continue;
+        }
+
+        for (position, window) in words.windows(from.len()).enumerate() {
+            if !window
+                .iter()
+                .copied()
+                .eq(from.iter().map(|word| Some(word.as_str())))
+            {
+                continue;
+            }
+            for synonym in synonyms {
+                // a multi-word synonym only matches the documents containing all its words
+                let words: Vec<_> = tokenizer.words(synonym).collect();
+                let docids = words
+                    .iter()
+                    .map(|word| index.word_docids(word))
+                    .reduce(|acc, bitmap| acc & bitmap)
+                    .unwrap_or_default();
+                terms.push(Term {
+                    span: position..position + from.len(),
+                    docids,
+                    cost: SYNONYM,
+                    words,
+                });
+            }
+        }
+    }
+
+    Ok(terms)
+}
+
+/// Every way of splitting a word in two parts that are long enough.
+fn splits(word: &str) -> impl Iterator<Item = (&str, &str)> {
+    let len = word.chars().count();
+    word.char_indices()
+        .enumerate()
+        .filter(move |(i, _)| *i >= MIN_SPLIT_LEN && len - i >= MIN_SPLIT_LEN)
+        .map(move |(_, (offset, _))| word.split_at(offset))
+}
@@ -0,0 +1,109 @@
+use regex::{Regex, RegexBuilder};
+
+use crate::{Index, Tokenizer};
+
+use super::SearchError;
+
+/// A wildcard or a regex matched against the words of the index.
+pub struct Pattern {
+    /// The pattern as the user typed it.
+    source: String,
+    /// Only the words starting with this prefix can match, it avoids going through the whole dictionary.
+    prefix: String,
+    regex: Regex,
+}
+
+/// The maximum size in bytes of a compiled pattern, and of the cache of the states it goes
+/// through, so a pattern can't take all the memory of the server.
+const SIZE_LIMIT: usize = 1 << 20;
+
+impl Pattern {
+    /// A word where `*` matches any number of chars and `?` exactly one char, like `colo?r`.
+    /// The other chars are normalized the same way as the words of the documents.
+    pub fn wildcard(source: &str, tokenizer: &dyn Tokenizer) -> Result<Self, SearchError> {
+        let normalize = |literal: &str| -> String {
+            tokenizer
+                .tokenize(literal)
+                .map(|token| token.normalized)
+                .collect()
+        };
+
+        let mut regex = String::new();
+        let mut prefix = None;
+        let mut literal = String::new();
+        for c in source.chars() {
+            if c != '*' && c != '?' {
+                literal.push(c);
+                continue;
+            }
+            let normalized = normalize(&literal);
+            literal.clear();
+            regex.push_str(&escape(&normalized));
+            regex.push_str(if c == '*' { ".*" } else { "." });
+            prefix.get_or_insert(normalized);
+        }
+        regex.push_str(&escape(&normalize(&literal)));
+
+        Self::new(source, prefix.unwrap_or_default(), &regex)
+    }
+
+    /// A regex matching the whole words, the words of the index are lowercased
+    /// so the regex must be written in lowercase.
+    pub fn regex(source: &str) -> Result<Self, SearchError> {
+        Self::new(source, String::new(), source)
+    }
+
+    fn new(source: &str, prefix: String, regex: &str) -> Result<Self, SearchError> {
+        let regex = RegexBuilder::new(&format!("^(?:{regex})$"))
+            .size_limit(SIZE_LIMIT)
+            .dfa_size_limit(SIZE_LIMIT)
+            .build()
+            .map_err(|error| SearchError::InvalidPattern {
+                pattern: source.to_string(),
+                error: error.to_string(),
+            })?;
+
+        Ok(Pattern {
+            source: source.to_string(),
+            prefix,
+            regex,
+        })
+    }
+
+    /// Returns `true` if the whole word matches the pattern.
+    fn matches(&self, word: &str) -> bool {
+        self.regex.is_match(word)
+    }
+
+    /// The words of the index matching the pattern. It fails if more than `limit` words match.
+    pub fn expand<I: Index>(&self, index: &I, limit: usize) -> Result<Vec<String>, SearchError> {
+        let mut words = Vec::new();
+
+        for word in index.words_starting_with(&self.prefix) {
+            if !self.matches(&word) {
+                continue;
+            }
+            if words.len() == limit {
+                return Err(SearchError::TooManyExpansions {
+                    pattern: self.source.clone(),
+                    limit,
+                });
+            }
+            words.push(word);
+        }
+
+        Ok(words)
+    }
+}
+
+/// Escape the chars having a meaning in a regex.
+fn escape(literal: &str) -> String {
+    let mut escaped = String::new();
+    for c in literal.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
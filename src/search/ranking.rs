@@ -0,0 +1,313 @@
+use std::cmp::Ordering;
+
+use roaring::RoaringBitmap;
+
+use crate::{
+    geo::{GeoSort, GEO_LAT_FIELD, GEO_LNG_FIELD},
+    CustomRule, DocId, Index, Order, RankingData, Settings,
+};
+
+use super::{Term, SYNONYM};
+
+/// The number of steps of the scores of the attribute and custom ranking rules.
+const SCORE_PRECISION: f64 = 100.0;
+
+/// How relevant a document is to a query, compared field by field.
+pub(super) struct Ranked {
+    pub docid: DocId,
+    /// The number of query words matched by the document.
+    pub matched: usize,
+    /// The sum of the costs of the cheapest term matching each word.
+    pub cost: u32,
+    /// The sum of the boosts of the attributes matching each word.
+    pub weight: f64,
+    /// The values of the attributes of the custom ranking.
+    pub values: Vec<Option<f64>>,
+    /// The distance to the point of the geo sort, in meters.
+    pub distance: Option<f64>,
+}
+
+/// How well a document does on one ranking rule, from 0 to `max_rank`.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct RuleScore {
+    pub rank: u32,
+    pub max_rank: u32,
+}
+
+impl RuleScore {
+    /// The rank normalized between 0 and 1.
+    pub fn score(&self) -> f64 {
+        if self.max_rank == 0 {
+            1.0
+        } else {
+            self.rank as f64 / self.max_rank as f64
+        }
+    }
+
+    /// Quantize a score between 0 and 1.
+    fn from_score(score: f64) -> Self {
+        RuleScore {
+            rank: (score * SCORE_PRECISION).round() as u32,
+            max_rank: SCORE_PRECISION as u32,
+        }
+    }
+}
+
+/// The range of values of each ranking rule, to normalize the scores of the documents.
+/// The ranges are the ones of the whole index, so a score doesn't depend on the other
+/// documents found.
+pub(super) struct Scale<'a> {
+    settings: &'a Settings,
+    sort: Option<&'a GeoSort>,
+    nb_words: usize,
+    /// The lowest and highest weight of a word.
+    boosts: (f64, f64),
+    /// The lowest and highest value of each custom ranking rule in the index.
+    values: Vec<Option<(f64, f64)>>,
+    /// The closest and farthest distances of the documents of the index to the geo sort.
+    distances: Option<(f64, f64)>,
+}
+
+impl<'a> Scale<'a> {
+    pub fn new<I: Index>(
+        index: &I,
+        settings: &'a Settings,
+        sort: Option<&'a GeoSort>,
+        nb_words: usize,
+    ) -> Self {
+        // the attributes without a boost weigh 1
+        let boosts = settings
+            .attribute_boosts
+            .values()
+            .fold((1.0_f64, 1.0_f64), |(min, max), boost| {
+                (min.min(*boost), max.max(*boost))
+            });
+        let values = settings
+            .custom_ranking
+            .iter()
+            .map(|rule| index.number_bounds(&rule.field))
+            .collect();
+        // the documents are somewhere in the box of their coordinates
+        let distances = sort.and_then(|sort| {
+            let lat = index.number_bounds(GEO_LAT_FIELD)?;
+            let lng = index.number_bounds(GEO_LNG_FIELD)?;
+            Some(sort.point.distance_bounds(lat, lng))
+        });
+
+        Scale {
+            settings,
+            sort,
+            nb_words,
+            boosts,
+            values,
+            distances,
+        }
+    }
+
+    pub fn custom_ranking(&self) -> &[CustomRule] {
+        &self.settings.custom_ranking
+    }
+
+    pub fn sort(&self) -> Option<&GeoSort> {
+        self.sort
+    }
+
+    /// The score of the document on each ranking rule, in the order they are applied.
+    pub fn rules(&self, ranked: &Ranked) -> Vec<RuleScore> {
+        let mut rules = Vec::with_capacity(3 + self.values.len());
+
+        rules.push(RuleScore {
+            rank: ranked.matched as u32,
+            max_rank: self.nb_words as u32,
+        });
+
+        if let Some(sort) = self.sort {
+            rules.push(ranged(sort.order, ranked.distance, self.distances));
+        }
+
+        // every word can at most be matched by the most expensive term
+        let max_cost = SYNONYM as u32 * ranked.matched as u32;
+        rules.push(RuleScore {
+            rank: max_cost - ranked.cost,
+            max_rank: max_cost,
+        });
+
+        let (min, max) = self.boosts;
+        let (min, max) = (min * ranked.matched as f64, max * ranked.matched as f64);
+        rules.push(if min < max {
+            RuleScore::from_score((ranked.weight - min) / (max - min))
+        } else {
+            RuleScore {
+                rank: 0,
+                max_rank: 0,
+            }
+        });
+
+        for ((rule, value), bounds) in self
+            .settings
+            .custom_ranking
+            .iter()
+            .zip(&ranked.values)
+            .zip(&self.values)
+        {
+            rules.push(ranged(rule.order, *value, *bounds));
+        }
+
+        rules
+    }
+
+    /// The score of the document between 0 and 1, the rules applied first weigh more
+    /// than all the following ones together.
+    pub fn score(&self, ranked: &Ranked) -> f64 {
+        let (mut score, mut max, mut weight) = (0.0, 0.0, 1.0);
+        for rule in self.rules(ranked) {
+            weight /= (rule.max_rank + 1) as f64;
+            score += rule.rank as f64 * weight;
+            max += rule.max_rank as f64 * weight;
+        }
+
+        if max == 0.0 {
+            1.0
+        } else {
+            score / max
+        }
+    }
+}
+
+/// The score of a value between the lowest and highest values of the index,
+/// the documents without a value get the lowest score.
+fn ranged(order: Order, value: Option<f64>, bounds: Option<(f64, f64)>) -> RuleScore {
+    match (value, bounds) {
+        (Some(value), Some((min, max))) if min < max => RuleScore::from_score(match order {
+            Order::Asc => ((max - value) / (max - min)).clamp(0.0, 1.0),
+            Order::Desc => ((value - min) / (max - min)).clamp(0.0, 1.0),
+        }),
+        (Some(_), _) => RuleScore {
+            rank: 0,
+            max_rank: 0,
+        },
+        (None, _) => RuleScore::from_score(0.0),
+    }
+}
+
+/// Sort the documents by the number of query words they match, then by the geo sort,
+/// then by how close to the query these matches are, then by the boosts of the attributes
+/// they match in and finally by the custom ranking. Only the documents in the `filter`
+/// are ranked, and a query without words returns all of them.
+pub(super) fn rank<I: Index>(
+    index: &I,
+    settings: &Settings,
+    terms: &[Term],
+    nb_words: usize,
+    filter: Option<&RoaringBitmap>,
+    sort: Option<&GeoSort>,
+) -> Vec<Ranked> {
+    let matching = terms
+        .iter()
+        .fold(RoaringBitmap::new(), |acc, term| acc | &term.docids);
+    let candidates = match filter {
+        Some(filter) if nb_words == 0 => filter.clone(),
+        Some(filter) => matching & filter,
+        None => matching,
+    };
+    // without any boost, custom ranking nor geo sort there is no need to fetch the ranking data
+    let needs_data = !settings.attribute_boosts.is_empty()
+        || !settings.custom_ranking.is_empty()
+        || sort.is_some();
+
+    // the data of all the candidates is read at once, in the order of their ids
+    let mut data = if needs_data {
+        index.ranking_data_batch(&candidates)
+    } else {
+        Vec::new()
+    }
+    .into_iter();
+
+    let mut ranked: Vec<_> = candidates
+        .into_iter()
+        .map(|docid| {
+            let data = data.next().unwrap_or_default();
+
+            // for each word of the query we keep the cheapest term and the heaviest
+            // attribute matching the document
+            let mut costs = vec![None; nb_words];
+            let mut weights = vec![0.0_f64; nb_words];
+            for term in terms.iter().filter(|term| term.docids.contains(docid)) {
+                let weight = attribute_weight(settings, &data, term);
+                for position in term.span.clone() {
+                    let cost = &mut costs[position];
+                    *cost = Some(cost.map_or(term.cost, |cost: u8| cost.min(term.cost)));
+                    weights[position] = weights[position].max(weight);
+                }
+            }
+
+            Ranked {
+                docid,
+                matched: costs.iter().flatten().count(),
+                cost: costs.iter().flatten().map(|cost| *cost as u32).sum(),
+                weight: weights.iter().sum(),
+                values: settings
+                    .custom_ranking
+                    .iter()
+                    .map(|rule| data.numbers.get(&rule.field).copied())
+                    .collect(),
+                distance: sort
+                    .zip(data.geo)
+                    .map(|(sort, point)| sort.point.distance(&point)),
+            }
+        })
+        .collect();
+
+    ranked.sort_unstable_by(|left, right| {
+        right
+            .matched
+            .cmp(&left.matched)
+            .then_with(|| match sort {
+                Some(sort) => compare(sort.order, left.distance, right.distance),
+                None => Ordering::Equal,
+            })
+            .then(left.cost.cmp(&right.cost))
+            .then(right.weight.total_cmp(&left.weight))
+            .then_with(|| {
+                settings
+                    .custom_ranking
+                    .iter()
+                    .zip(left.values.iter().zip(&right.values))
+                    .map(|(rule, (left, right))| compare(rule.order, *left, *right))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            })
+            .then(left.docid.cmp(&right.docid))
+    });
+
+    ranked
+}
+
+/// The highest boost among the attributes of the document containing all the words of the
+/// term, a term only found in the attributes without a boost weighs 1.
+fn attribute_weight(settings: &Settings, data: &RankingData, term: &Term) -> f64 {
+    settings
+        .attribute_boosts
+        .iter()
+        .filter(|(attribute, _)| {
+            data.attributes
+                .get(*attribute)
+                .is_some_and(|words| term.words.iter().all(|word| words.contains(word)))
+        })
+        .map(|(_, boost)| *boost)
+        .reduce(f64::max)
+        .unwrap_or(1.0)
+}
+
+/// Compare two values of a custom ranking rule or of the geo sort, the missing values come last.
+fn compare(order: Order, left: Option<f64>, right: Option<f64>) -> Ordering {
+    match (left, right) {
+        (Some(left), Some(right)) => match order {
+            Order::Asc => left.total_cmp(&right),
+            Order::Desc => right.total_cmp(&left),
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use crate::{filter::Filter, DocId, Hit, Index};
+
+use super::SearchError;
+
+/// The number of words of the document looked for in the other documents.
+const MAX_TERMS: usize = 25;
+
+/// The documents sharing the most significant words of a document, the document itself excluded.
+/// A word weighs its number of occurrences in the document times its inverse document frequency,
+/// and the score of a document is the weight of the words it shares over the weight of all the
+/// words looked for. Returns `None` if the document doesn't exist.
+pub fn similar<I: Index>(
+    index: &I,
+    docid: DocId,
+    filter: Option<&str>,
+) -> Result<Option<Vec<Hit>>, SearchError> {
+    let filter = filter
+        .map(Filter::parse)
+        .transpose()
+        .map_err(SearchError::InvalidFilter)?;
+    let document = match index.get_document(docid) {
+        Some(document) => document,
+        None => return Ok(None),
+    };
+    let tokenizer = index.tokenizer(&index.settings());
+
+    let mut frequencies: HashMap<String, f64> = HashMap::new();
+    for word in document.fields().flat_map(|field| tokenizer.words(field)) {
+        *frequencies.entry(word).or_default() += 1.0;
+    }
+    let nb_documents = index.nb_documents() as f64;
+    let mut terms: Vec<_> = frequencies
+        .into_iter()
+        .map(|(word, frequency)| {
+            let docids = index.word_docids(&word);
+            let df = docids.len() as f64;
+            let idf = (1.0 + (nb_documents - df + 0.5) / (df + 0.5)).ln();
+            (frequency * idf, word, docids)
+        })
+        .collect();
+    terms.sort_unstable_by(|(left, left_word, _), (right, right_word, _)| {
+        right.total_cmp(left).then(left_word.cmp(right_word))
+    });
+    terms.truncate(MAX_TERMS);
+
+    let filter = filter.map(|filter| filter.docids(index));
+    let total: f64 = terms.iter().map(|(weight, _, _)| weight).sum();
+    let mut scores: HashMap<DocId, f64> = HashMap::new();
+    for (weight, _, mut docids) in terms {
+        docids.remove(docid);
+        if let Some(filter) = &filter {
+            docids &= filter;
+        }
+        for other in docids {
+            *scores.entry(other).or_default() += weight;
+        }
+    }
+
+    let mut hits: Vec<_> = scores
+        .into_iter()
+        .map(|(docid, score)| Hit {
+            docid,
+            score: if total > 0.0 { score / total } else { 0.0 },
+            details: None,
+            pinned: false,
+            geo_distance: None,
+            semantic_score: None,
+        })
+        .collect();
+    hits.sort_unstable_by(|left, right| {
+        right
+            .score
+            .total_cmp(&left.score)
+            .then(left.docid.cmp(&right.docid))
+    });
+    Ok(Some(hits))
+}
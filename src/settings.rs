@@ -0,0 +1,203 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::tokenizer::{default_separators, Language, Separators};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    /// Map a word, or a group of words, to the list of its synonyms.
+    /// A mapping only works in one way, `"nyc": ["new york"]` won't make `new york`
+    /// match `nyc`. The synonyms can also be sent as groups of equivalent expressions,
+    /// like `[["sf", "science fiction"]]`, which are mapped in every direction.
+    #[serde(deserialize_with = "deserialize_synonyms")]
+    pub synonyms: BTreeMap<String, Vec<String>>,
+    /// The language used to stem the words, no stemming is done if it's not set.
+    pub language: Option<Language>,
+    /// How the hyphens, apostrophes, dots and the other separators are handled inside the words.
+    pub separators: Separators,
+    /// Words added to the dictionaries used to segment the chinese, japanese and thai texts.
+    pub dictionary: BTreeSet<String>,
+    /// The locale of the documents, like `tr` or `en-US`, it changes how the words are lowercased.
+    pub locale: Option<String>,
+    /// Index the words with their accents on top of the words without accents, so the
+    /// documents matching the accents of the query rank first.
+    pub keep_accents: bool,
+    /// Index the identifiers like `getUserName` or `user_name` both whole and split into sub-words.
+    pub split_identifiers: bool,
+    /// Allow the words of the queries surrounded by `/` to be regexes, like `/colou?r/`.
+    pub allow_regex: bool,
+    /// The maximum number of words a wildcard or a regex of a query can match.
+    pub max_expansions: usize,
+    /// The weight of the matches in each attribute, like `"title": 3`, the attributes
+    /// that are not listed weigh 1.
+    pub attribute_boosts: BTreeMap<String, f64>,
+    /// The numeric attributes used to sort the documents that are equally relevant,
+    /// like `popularity:desc`, in order of importance.
+    pub custom_ranking: Vec<CustomRule>,
+    /// The attributes indexed by value, they can be used to deduplicate the documents.
+    pub faceted_attributes: BTreeSet<String>,
+    /// Only keep the best document for each value of this attribute, it's always faceted.
+    pub distinct: Option<String>,
+    /// The number of dimensions of the `_vectors` of the documents, they're ignored if it's not set.
+    pub vector_dimensions: Option<usize>,
+}
+
+/// Sort the documents by a numeric attribute, the documents without this attribute come last.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CustomRule {
+    pub field: String,
+    pub order: Order,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+impl TryFrom<String> for CustomRule {
+    type Error = String;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        let (field, order) = match rule.rsplit_once(':') {
+            Some((field, "asc")) if !field.is_empty() => (field, Order::Asc),
+            Some((field, "desc")) if !field.is_empty() => (field, Order::Desc),
+            _ => {
+                return Err(format!(
+                    "Invalid ranking rule `{rule}`, expected `attribute:asc` or `attribute:desc`"
+                ))
+            }
+        };
+        Ok(CustomRule {
+            field: field.to_string(),
+            order,
+        })
+    }
+}
+
+impl fmt::Display for CustomRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.order {
+            Order::Asc => write!(f, "{}:asc", self.field),
+            Order::Desc => write!(f, "{}:desc", self.field),
+        }
+    }
+}
+
+impl From<CustomRule> for String {
+    fn from(rule: CustomRule) -> Self {
+        rule.to_string()
+    }
+}
+
+fn deserialize_synonyms<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, Vec<String>>, D::Error> {
+    struct Visitor;
+
+    impl<'de> de::Visitor<'de> for Visitor {
+        type Value = BTreeMap<String, Vec<String>>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map from a word to its synonyms or an array of groups of synonyms")
+        }
+
+        fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut synonyms = BTreeMap::new();
+            while let Some((word, words)) = map.next_entry()? {
+                synonyms.insert(word, words);
+            }
+            Ok(synonyms)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut synonyms = BTreeMap::<String, Vec<String>>::new();
+            while let Some(group) = seq.next_element::<Vec<String>>()? {
+                for word in &group {
+                    let words = synonyms.entry(word.clone()).or_default();
+                    for other in &group {
+                        if other != word && !words.contains(other) {
+                            words.push(other.clone());
+                        }
+                    }
+                }
+            }
+            Ok(synonyms)
+        }
+    }
+
+    deserializer.deserialize_any(Visitor)
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            synonyms: BTreeMap::new(),
+            language: None,
+            separators: default_separators(),
+            dictionary: BTreeSet::new(),
+            locale: None,
+            keep_accents: false,
+            split_identifiers: false,
+            allow_regex: false,
+            max_expansions: 1000,
+            attribute_boosts: BTreeMap::new(),
+            custom_ranking: Vec::new(),
+            faceted_attributes: BTreeSet::new(),
+            distinct: None,
+            vector_dimensions: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Apply a partial update on top of the current settings.
+    /// Only the fields present in the update are replaced.
+    pub fn update(&self, update: serde_json::Map<String, Value>) -> serde_json::Result<Self> {
+        let mut settings = match serde_json::to_value(self)? {
+            Value::Object(settings) => settings,
+            _ => unreachable!("Settings are always serialized as an object"),
+        };
+        settings.extend(update);
+        serde_json::from_value(Value::Object(settings))
+    }
+
+    /// The attributes indexed by value.
+    pub fn faceted(&self) -> BTreeSet<&str> {
+        self.faceted_attributes
+            .iter()
+            .chain(&self.distinct)
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// The attributes of the custom ranking, whatever their order.
+    fn ranked_attributes(&self) -> BTreeSet<&str> {
+        self.custom_ranking
+            .iter()
+            .map(|rule| rule.field.as_str())
+            .collect()
+    }
+
+    /// Returns `true` if the documents must be indexed again to apply the new settings.
+    pub fn requires_reindexing(&self, new: &Settings) -> bool {
+        self.language != new.language
+            || self.separators != new.separators
+            || self.dictionary != new.dictionary
+            || self.locale != new.locale
+            || self.keep_accents != new.keep_accents
+            || self.split_identifiers != new.split_identifiers
+            // only the boosted and ranked attributes are extracted, not their weight or order
+            || !self.attribute_boosts.keys().eq(new.attribute_boosts.keys())
+            || self.ranked_attributes() != new.ranked_attributes()
+            || self.faceted() != new.faceted()
+            || self.vector_dimensions != new.vector_dimensions
+    }
+}
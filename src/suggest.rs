@@ -0,0 +1,204 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, RwLock, RwLockReadGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use fst::{
+    automaton::{Levenshtein, Str},
+    Automaton, IntoStreamer, Map, Streamer,
+};
+use roaring::RoaringBitmap;
+use serde::Serialize;
+
+use crate::Index;
+
+/// The number of queries remembered to boost the suggestions.
+const RECENT_QUERIES: usize = 1000;
+/// The words longer than this, in chars, can be corrected with two typos instead of one.
+const TWO_TYPOS_LEN: usize = 5;
+/// The shorter prefixes, in chars, match too many words to be completed.
+const MIN_PREFIX_LEN: usize = 2;
+/// With a filter only this many of the most frequent words are counted among the documents
+/// matching it, unless more suggestions are asked for.
+const FILTERED_WORDS: usize = 100;
+/// The words of the index are rebuilt at most this often, the writes in between are only
+/// suggested once it's elapsed.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Complete the words being typed with the words of the index.
+pub struct Suggester {
+    /// The words of the index along with the number of documents containing them.
+    words: RwLock<Map<Vec<u8>>>,
+    /// Whether the index changed since the words were built.
+    stale: AtomicBool,
+    /// When the words were built.
+    built_at: Mutex<Instant>,
+    recent: Mutex<RecentQueries>,
+}
+
+/// The words of the latest queries that found something.
+#[derive(Default)]
+struct RecentQueries {
+    queries: VecDeque<Vec<String>>,
+    /// How many of the recent queries contain each word.
+    words: HashMap<String, u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Suggestion {
+    pub word: String,
+    /// The number of documents containing the word.
+    pub frequency: u64,
+}
+
+impl Suggester {
+    pub fn new<I: Index>(index: &I) -> Self {
+        Suggester {
+            words: RwLock::new(Self::build(index)),
+            stale: AtomicBool::new(false),
+            built_at: Mutex::new(Instant::now()),
+            recent: Mutex::default(),
+        }
+    }
+
+    fn build<I: Index>(index: &I) -> Map<Vec<u8>> {
+        Map::from_iter(index.word_frequencies()).expect("The words are not sorted")
+    }
+
+    /// Must be called after every write, the words are rebuilt from the index when they're
+    /// needed and at most once per `REFRESH_INTERVAL`.
+    pub fn invalidate(&self) {
+        self.stale.store(true, Ordering::Relaxed);
+    }
+
+    /// The words of the index, rebuilt if the index changed and they're old enough.
+    fn words<I: Index>(&self, index: &I) -> RwLockReadGuard<'_, Map<Vec<u8>>> {
+        if self.stale.load(Ordering::Relaxed) {
+            let mut built_at = self.built_at.lock().unwrap();
+            if built_at.elapsed() >= REFRESH_INTERVAL && self.stale.swap(false, Ordering::Relaxed) {
+                *self.words.write().unwrap() = Self::build(index);
+                *built_at = Instant::now();
+            }
+        }
+        self.words.read().unwrap()
+    }
+
+    /// Remember the words of a query to boost them in the suggestions.
+    pub fn record(&self, mut words: Vec<String>) {
+        words.sort_unstable();
+        words.dedup();
+
+        let mut recent = self.recent.lock().unwrap();
+        for word in &words {
+            *recent.words.entry(word.clone()).or_default() += 1;
+        }
+        recent.queries.push_back(words);
+
+        if recent.queries.len() > RECENT_QUERIES {
+            for word in recent.queries.pop_front().unwrap() {
+                if let Some(count) = recent.words.get_mut(&word) {
+                    *count -= 1;
+                    if *count == 0 {
+                        recent.words.remove(&word);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The words starting with the prefix, the most frequent first. The frequency of
+    /// the words is multiplied by the number of recent queries containing them.
+    /// If some candidates are given only the documents among them are counted, for the
+    /// `FILTERED_WORDS` most frequent words of the index.
+    pub fn suggest<I: Index>(
+        &self,
+        index: &I,
+        prefix: &str,
+        candidates: Option<&RoaringBitmap>,
+        limit: usize,
+    ) -> Vec<Suggestion> {
+        if prefix.chars().count() < MIN_PREFIX_LEN {
+            return Vec::new();
+        }
+        let words = self.words(index);
+        let mut stream = words.search(Str::new(prefix).starts_with()).into_stream();
+
+        let recent = self.recent.lock().unwrap();
+        let score = |suggestion: &Suggestion| {
+            suggestion.frequency * (1 + recent.words.get(&suggestion.word).unwrap_or(&0))
+        };
+        // only the best words are kept while streaming, the worst one on top of the heap
+        let kept = match candidates {
+            Some(_) => FILTERED_WORDS.max(limit),
+            None => limit,
+        };
+        let mut best = BinaryHeap::with_capacity(kept + 1);
+        while let Some((word, frequency)) = stream.next() {
+            let suggestion = Suggestion {
+                word: String::from_utf8(word.to_vec()).unwrap(),
+                frequency,
+            };
+            best.push(Reverse((
+                score(&suggestion),
+                Reverse(suggestion.word),
+                frequency,
+            )));
+            if best.len() > kept {
+                best.pop();
+            }
+        }
+
+        let mut suggestions: Vec<_> = best
+            .into_iter()
+            .filter_map(|Reverse((_, Reverse(word), frequency))| {
+                let frequency = match candidates {
+                    Some(candidates) => (&index.word_docids(&word) & candidates).len(),
+                    None => frequency,
+                };
+                (frequency > 0).then_some(Suggestion { word, frequency })
+            })
+            .collect();
+        suggestions.sort_by(|left, right| {
+            score(right)
+                .cmp(&score(left))
+                .then_with(|| left.word.cmp(&right.word))
+        });
+        suggestions.truncate(limit);
+        suggestions
+    }
+
+    /// The closest word of the index to a word that is not in the index, the
+    /// words with less typos are preferred and then the most frequent ones.
+    pub fn correct<I: Index>(&self, index: &I, word: &str) -> Option<String> {
+        let words = self.words(index);
+        if words.contains_key(word) {
+            return None;
+        }
+
+        let max_typos = if word.chars().count() > TWO_TYPOS_LEN {
+            2
+        } else {
+            1
+        };
+        for typos in 1..=max_typos {
+            // the automaton can't be built for the very long words
+            let automaton = Levenshtein::new(word, typos).ok()?;
+            let mut stream = words.search(automaton).into_stream();
+
+            let mut candidates = Vec::new();
+            while let Some((candidate, frequency)) = stream.next() {
+                candidates.push((Reverse(frequency), candidate.to_vec()));
+            }
+            if let Some((_, candidate)) = candidates.into_iter().min() {
+                return Some(String::from_utf8(candidate).unwrap());
+            }
+        }
+
+        None
+    }
+}
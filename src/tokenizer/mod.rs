@@ -0,0 +1,399 @@
+mod segmenter;
+mod stemmer;
+
+use std::{collections::BTreeMap, fmt, iter, ops::Range};
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use segmenter::{Script, Segmenter};
+pub use stemmer::Language;
+
+use crate::Settings;
+
+/// A piece of text produced by a tokenizer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// The byte span of the token in the original text.
+    pub span: Range<usize>,
+    /// The normalized form of the token, this is what gets indexed.
+    pub normalized: String,
+    /// The normalized form of the token with its accents, it's indexed along the
+    /// normalized form. Only set when it differs from the normalized form.
+    pub accented: Option<String>,
+    /// The sub-words of a compound identifier, like `get`, `user` and `name` for `getUserName`.
+    /// They're indexed along the normalized form.
+    pub sub_words: Vec<String>,
+    /// The position of the word in the text, the separators share the position of the word following them.
+    pub position: usize,
+    pub kind: TokenKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Word,
+    Number,
+    Separator,
+}
+
+impl Token {
+    /// Returns `true` if the token is a word or a number.
+    pub fn is_word(&self) -> bool {
+        self.kind != TokenKind::Separator
+    }
+}
+
+pub trait Tokenizer: fmt::Debug + Send + Sync {
+    /// Split a text into tokens, in the order they appear in the text.
+    fn tokenize<'a>(&'a self, content: &'a str) -> Box<dyn Iterator<Item = Token> + 'a>;
+
+    /// The normalized words of a text, this is what gets indexed and searched for.
+    fn words<'a>(&'a self, content: &'a str) -> Box<dyn Iterator<Item = String> + 'a> {
+        Box::new(
+            self.tokenize(content)
+                .filter(Token::is_word)
+                .map(|token| token.normalized),
+        )
+    }
+}
+
+/// How the characters that are neither letters nor digits are handled inside a word.
+/// The characters without any rule are removed and the words around them are joined.
+pub type Separators = BTreeMap<char, SeparatorRule>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SeparatorRule {
+    /// The words around the separator are split, `spider-man` gives `spider` and `man`.
+    Split,
+    /// The separator is removed and the words around it are joined, `spider-man` gives `spiderman`.
+    Join,
+    /// The separator is kept inside the word, `spider-man` gives `spider-man`.
+    Keep,
+    /// The separator is kept between two digits and splits the words otherwise,
+    /// `3.5` stays `3.5` while `end.start` gives `end` and `start`.
+    KeepBetweenDigits,
+}
+
+pub fn default_separators() -> Separators {
+    BTreeMap::from([
+        ('-', SeparatorRule::Split),
+        ('\'', SeparatorRule::Join),
+        ('.', SeparatorRule::KeepBetweenDigits),
+    ])
+}
+
+/// The tokenizer used when none is configured. The text is normalized with NFKC and
+/// split on the whitespaces, the words are lowercased, their accents are removed and
+/// the separators inside them are handled by following the separator rules of the settings.
+/// The chinese, japanese and thai texts are segmented with a dictionary instead,
+/// and like korean they are kept in their original script.
+#[derive(Debug, Clone)]
+pub struct DefaultTokenizer {
+    separators: Separators,
+    segmenter: Segmenter,
+    keep_accents: bool,
+    split_identifiers: bool,
+    /// The turkic languages lowercase `I` to a dotless `ı`.
+    turkic: bool,
+}
+
+impl DefaultTokenizer {
+    pub fn new(settings: &Settings) -> Self {
+        let language = settings
+            .locale
+            .as_deref()
+            .and_then(|locale| locale.split(['-', '_']).next());
+
+        DefaultTokenizer {
+            separators: settings.separators.clone(),
+            segmenter: Segmenter::new(&settings.dictionary),
+            keep_accents: settings.keep_accents,
+            split_identifiers: settings.split_identifiers,
+            turkic: matches!(language, Some("tr" | "az")),
+        }
+    }
+}
+
+/// A normalized char along with the span of the text it comes from.
+struct NormalizedChar {
+    c: char,
+    span: Range<usize>,
+    /// Whether the char comes from an uppercase char, to find the sub-words of the identifiers.
+    uppercase: bool,
+    /// The lowercased text the char comes from, with its accents. It's only set on
+    /// the first char produced by a piece of text, and only if the accents are kept.
+    accented: Option<String>,
+}
+
+/// Split a text into chars followed by their combining marks, normalized with NFKC.
+fn clusters(content: &str) -> impl Iterator<Item = (Range<usize>, String)> + '_ {
+    let mut chars = content.char_indices().peekable();
+    iter::from_fn(move || {
+        let (start, c) = chars.next()?;
+        let mut end = start + c.len_utf8();
+        while let Some((offset, c)) = chars.next_if(|(_, c)| is_combining_mark(*c)) {
+            end = offset + c.len_utf8();
+        }
+        Some((start..end, content[start..end].nfkc().collect()))
+    })
+}
+
+impl Tokenizer for DefaultTokenizer {
+    fn tokenize<'a>(&'a self, content: &'a str) -> Box<dyn Iterator<Item = Token> + 'a> {
+        let mut tokens = Tokens {
+            split_identifiers: self.split_identifiers,
+            ..Tokens::default()
+        };
+        // the normalized chars of the current chunk of text along with the span of the char they come from
+        let mut chunk = Vec::new();
+        // the chars of the current run of text written without spaces, they are all of the same script
+        let mut run = Vec::new();
+
+        for (span, cluster) in clusters(content) {
+            for c in cluster.chars() {
+                let script = Script::of(c);
+
+                if script.is_segmented() {
+                    if run.last().is_some_and(|(c, _)| Script::of(*c) != script) {
+                        self.segment_run(&run, &mut tokens);
+                        run.clear();
+                    }
+                    self.split_chunk(&chunk, &mut tokens);
+                    chunk.clear();
+                    run.push((c, span.clone()));
+                    continue;
+                }
+                self.segment_run(&run, &mut tokens);
+                run.clear();
+
+                if c.is_whitespace() {
+                    self.split_chunk(&chunk, &mut tokens);
+                    chunk.clear();
+                    tokens.push_separator(span.clone(), c);
+                } else if script == Script::Hangul {
+                    chunk.push(NormalizedChar {
+                        c,
+                        span: span.clone(),
+                        uppercase: false,
+                        accented: None,
+                    });
+                } else {
+                    self.normalize(c, span.clone(), &mut chunk);
+                }
+            }
+        }
+        self.segment_run(&run, &mut tokens);
+        self.split_chunk(&chunk, &mut tokens);
+
+        Box::new(tokens.tokens.into_iter())
+    }
+}
+
+impl DefaultTokenizer {
+    /// Lowercase a char and remove its accents.
+    fn normalize(&self, c: char, span: Range<usize>, chunk: &mut Vec<NormalizedChar>) {
+        let uppercase = c.is_uppercase();
+        let lowercase = match c {
+            'I' if self.turkic => String::from("ı"),
+            // the default lowercasing keeps the dot as a combining char
+            'İ' => String::from("i"),
+            c => c.to_lowercase().collect(),
+        };
+        // some chars are transliterated to uppercase letters, like `Ω` to `O`
+        let folded = deunicode::deunicode(&lowercase).to_lowercase();
+
+        let mut accented = self.keep_accents.then_some(lowercase);
+        for c in folded.chars() {
+            chunk.push(NormalizedChar {
+                c,
+                span: span.clone(),
+                uppercase,
+                accented: accented.take(),
+            });
+        }
+    }
+
+    /// Split a normalized chunk of text into words by following the separator rules.
+    fn split_chunk(&self, chunk: &[NormalizedChar], tokens: &mut Tokens) {
+        // the chars of the current word and whether they are part of its normalized form
+        let mut word = Vec::new();
+
+        for (i, c) in chunk.iter().enumerate() {
+            match self.rule(chunk, i) {
+                SeparatorRule::Keep => word.push((c, true)),
+                SeparatorRule::Join => word.push((c, false)),
+                _ => {
+                    tokens.push_word(&word);
+                    word.clear();
+                    tokens.push_separator(c.span.clone(), c.c);
+                }
+            }
+        }
+        tokens.push_word(&word);
+    }
+
+    /// Split a run of text written without spaces into words with the dictionary.
+    fn segment_run(&self, run: &[(char, Range<usize>)], tokens: &mut Tokens) {
+        let chars: Vec<_> = run.iter().map(|(c, _)| *c).collect();
+        for word in self.segmenter.segment(&chars) {
+            let span = run[word.start].1.start..run[word.end - 1].1.end;
+            tokens.push_segment(span, chars[word].iter().collect());
+        }
+    }
+
+    /// The rule to apply on the char at `index` in the chunk, letters and digits are always kept.
+    fn rule(&self, chunk: &[NormalizedChar], index: usize) -> SeparatorRule {
+        let c = chunk[index].c;
+        if c.is_alphanumeric() {
+            return SeparatorRule::Keep;
+        }
+
+        match self.separators.get(&c) {
+            Some(SeparatorRule::KeepBetweenDigits) => {
+                let previous = index.checked_sub(1).map(|i| chunk[i].c);
+                let next = chunk.get(index + 1).map(|c| c.c);
+                if previous.is_some_and(|c| c.is_ascii_digit())
+                    && next.is_some_and(|c| c.is_ascii_digit())
+                {
+                    SeparatorRule::Keep
+                } else {
+                    SeparatorRule::Split
+                }
+            }
+            Some(rule) => *rule,
+            None => SeparatorRule::Join,
+        }
+    }
+}
+
+/// The tokens produced so far.
+#[derive(Default)]
+struct Tokens {
+    tokens: Vec<Token>,
+    position: usize,
+    split_identifiers: bool,
+}
+
+impl Tokens {
+    /// Push a separator, it's merged with the previous separator if they are contiguous.
+    fn push_separator(&mut self, span: Range<usize>, c: char) {
+        match self.tokens.last_mut() {
+            Some(token) if token.kind == TokenKind::Separator && span.start <= token.span.end => {
+                token.span.end = token.span.end.max(span.end);
+                token.normalized.push(c);
+            }
+            _ => self.tokens.push(Token {
+                span,
+                normalized: c.to_string(),
+                accented: None,
+                sub_words: Vec::new(),
+                position: self.position,
+                kind: TokenKind::Separator,
+            }),
+        }
+    }
+
+    /// Push a word coming from a segmented text, its chars are all kept even
+    /// if they aren't alphanumeric, like the thai tone marks.
+    fn push_segment(&mut self, span: Range<usize>, normalized: String) {
+        self.tokens.push(Token {
+            span,
+            normalized,
+            accented: None,
+            sub_words: Vec::new(),
+            position: self.position,
+            kind: TokenKind::Word,
+        });
+        self.position += 1;
+    }
+
+    /// Push a word, the separators at its edges are pushed as separators.
+    fn push_word(&mut self, chars: &[(&NormalizedChar, bool)]) {
+        let first = chars.iter().position(|(c, _)| c.c.is_alphanumeric());
+        let last = chars.iter().rposition(|(c, _)| c.c.is_alphanumeric());
+        let (first, last) = match first.zip(last) {
+            Some(edges) => edges,
+            None => {
+                chars
+                    .iter()
+                    .for_each(|(c, _)| self.push_separator(c.span.clone(), c.c));
+                return;
+            }
+        };
+
+        chars[..first]
+            .iter()
+            .for_each(|(c, _)| self.push_separator(c.span.clone(), c.c));
+
+        let word = &chars[first..=last];
+        let normalized: String = word
+            .iter()
+            .filter(|(_, keep)| *keep)
+            .map(|(c, _)| c.c)
+            .collect();
+        let accented: String = word
+            .iter()
+            .filter(|(_, keep)| *keep)
+            .filter_map(|(c, _)| c.accented.as_deref())
+            .collect();
+        let kind = if normalized
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .all(|c| c.is_ascii_digit())
+        {
+            TokenKind::Number
+        } else {
+            TokenKind::Word
+        };
+        self.tokens.push(Token {
+            span: word[0].0.span.start..word[word.len() - 1].0.span.end,
+            accented: (!accented.is_empty() && accented != normalized).then_some(accented),
+            sub_words: if self.split_identifiers {
+                sub_words(word)
+            } else {
+                Vec::new()
+            },
+            normalized,
+            position: self.position,
+            kind,
+        });
+        self.position += 1;
+
+        chars[last + 1..]
+            .iter()
+            .for_each(|(c, _)| self.push_separator(c.span.clone(), c.c));
+    }
+}
+
+/// Split a word on the separators joined inside it and on its case changes,
+/// `get_HTTPResponse` gives `get`, `http` and `response`. Nothing is returned
+/// if the word can't be split.
+fn sub_words(word: &[(&NormalizedChar, bool)]) -> Vec<String> {
+    let mut sub_words = vec![String::new()];
+
+    for (i, (c, keep)) in word.iter().enumerate() {
+        if !keep {
+            sub_words.push(String::new());
+            continue;
+        }
+        let previous = i.checked_sub(1).map(|i| word[i]).filter(|(_, keep)| *keep);
+        let next = word.get(i + 1).filter(|(_, keep)| *keep);
+        // `userName` is split before the `N`
+        let camel_case = c.uppercase && previous.is_some_and(|(previous, _)| !previous.uppercase);
+        // `HTTPResponse` is split before the `R`
+        let acronym_end = c.uppercase
+            && previous.is_some_and(|(previous, _)| previous.uppercase)
+            && next.is_some_and(|(next, _)| !next.uppercase && next.c.is_alphabetic());
+        if camel_case || acronym_end {
+            sub_words.push(String::new());
+        }
+        sub_words.last_mut().unwrap().push(c.c);
+    }
+
+    sub_words.retain(|sub_word| !sub_word.is_empty());
+    if sub_words.len() < 2 {
+        sub_words.clear();
+    }
+    sub_words
+}
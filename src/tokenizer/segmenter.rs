@@ -0,0 +1,191 @@
+//! Segmentation of the scripts written without spaces between their words.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    ops::Range,
+};
+
+use icu_segmenter::WordSegmenter;
+
+lazy_static::lazy_static!(
+    /// The dictionaries of ICU for the chinese, japanese and southeast asian scripts.
+    static ref DICTIONARIES: WordSegmenter = WordSegmenter::new_dictionary();
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Script {
+    /// Chinese, Japanese and Thai are written without spaces and must be segmented.
+    Cjk,
+    Thai,
+    /// Korean separates its words with spaces but must be kept in its original script.
+    Hangul,
+    Other,
+}
+
+impl Script {
+    pub fn of(c: char) -> Self {
+        match c as u32 {
+            // iteration marks, hiragana, katakana and their halfwidth forms, without the middle dot
+            0x3005..=0x3007 | 0x3040..=0x30FA | 0x30FC..=0x30FF | 0x31F0..=0x31FF => Script::Cjk,
+            0xFF66..=0xFF9F => Script::Cjk,
+            // han ideographs
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F => Script::Cjk,
+            0x0E00..=0x0E7F => Script::Thai,
+            0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+            _ => Script::Other,
+        }
+    }
+
+    /// Returns `true` if the words of this script are not separated by spaces.
+    pub fn is_segmented(&self) -> bool {
+        matches!(self, Script::Cjk | Script::Thai)
+    }
+}
+
+/// Split a run of text into words by looking for the longest word of the user at each
+/// position, the text between these words is segmented with the dictionaries of ICU.
+/// The ideographs ICU doesn't know any word for are indexed as overlapping bigrams,
+/// `ABC` gives `AB` and `BC`.
+#[derive(Debug, Clone)]
+pub struct Segmenter {
+    /// The words added by the user on top of the dictionaries of ICU.
+    words: HashSet<String>,
+    /// The length, in chars, of the longest word of the user.
+    longest: usize,
+}
+
+impl Segmenter {
+    pub fn new(dictionary: &BTreeSet<String>) -> Self {
+        let longest = dictionary
+            .iter()
+            .map(|word| word.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        Segmenter {
+            words: dictionary.iter().cloned().collect(),
+            longest,
+        }
+    }
+
+    /// Returns the range of chars of every word of the run.
+    pub fn segment(&self, run: &[char]) -> Vec<Range<usize>> {
+        let mut words = Vec::new();
+        // where the chars not covered by a word of the user started
+        let mut rest = 0;
+
+        let mut i = 0;
+        while i < run.len() {
+            let longest = (1..=self.longest.min(run.len() - i)).rev().find(|len| {
+                self.words
+                    .contains(&run[i..i + len].iter().collect::<String>())
+            });
+            match longest {
+                Some(len) => {
+                    words.extend(segment_with_icu(run, rest..i));
+                    words.push(i..i + len);
+                    i += len;
+                    rest = i;
+                }
+                None => i += 1,
+            }
+        }
+        words.extend(segment_with_icu(run, rest..run.len()));
+
+        words
+    }
+}
+
+/// The words of some chars of the run found by ICU. ICU splits the runs of ideographs it
+/// doesn't know in single chars, they're replaced by their bigrams.
+fn segment_with_icu(run: &[char], chars: Range<usize>) -> Vec<Range<usize>> {
+    if chars.is_empty() {
+        return Vec::new();
+    }
+    let text: String = run[chars.clone()].iter().collect();
+    // ICU breaks the text at byte offsets, they are converted back to offsets in chars
+    let mut offsets = text
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain([text.len()])
+        .enumerate();
+    let breaks: Vec<_> = DICTIONARIES
+        .segment_str(&text)
+        .filter_map(|byte| offsets.find(|(_, offset)| *offset == byte))
+        .map(|(index, _)| chars.start + index)
+        .collect();
+
+    let mut words = Vec::new();
+    // where the single ideographs found by ICU started
+    let mut unknown = None;
+    for word in breaks.windows(2).map(|word| word[0]..word[1]) {
+        if word.len() == 1 && is_ideograph(run[word.start]) {
+            unknown.get_or_insert(word.start);
+            continue;
+        }
+        if let Some(start) = unknown.take() {
+            words.extend(bigrams(start..word.start));
+        }
+        words.push(word);
+    }
+    if let Some(start) = unknown {
+        words.extend(bigrams(start..chars.end));
+    }
+    words
+}
+
+/// A lone ideograph is kept as is, it can be a word on its own.
+fn bigrams(chars: Range<usize>) -> Vec<Range<usize>> {
+    if chars.len() == 1 {
+        return vec![chars];
+    }
+    (chars.start..chars.end - 1).map(|i| i..i + 2).collect()
+}
+
+/// The han ideographs, without the kana whose single chars are often words on their own.
+fn is_ideograph(c: char) -> bool {
+    matches!(c as u32, 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::Segmenter;
+
+    fn words(segmenter: &Segmenter, text: &str) -> Vec<String> {
+        let run: Vec<_> = text.chars().collect();
+        segmenter
+            .segment(&run)
+            .into_iter()
+            .map(|word| run[word].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn japanese_title() {
+        let segmenter = Segmenter::new(&BTreeSet::new());
+        assert_eq!(
+            words(&segmenter, "千と千尋の神隠し"),
+            ["千", "と", "千尋", "の", "神隠し"]
+        );
+    }
+
+    #[test]
+    fn unknown_ideographs() {
+        let segmenter = Segmenter::new(&BTreeSet::new());
+        let document = words(&segmenter, "鬱龘靐齉爩");
+        assert_eq!(document, ["鬱龘", "龘靐", "靐齉", "齉爩"]);
+        // a part of the run is split the same way when it's searched
+        let query = words(&segmenter, "龘靐齉");
+        assert_eq!(query, ["龘靐", "靐齉"]);
+        assert!(query.iter().all(|word| document.contains(word)));
+    }
+
+    #[test]
+    fn user_words_first() {
+        let dictionary = BTreeSet::from(["靐齉".to_string()]);
+        let segmenter = Segmenter::new(&dictionary);
+        assert_eq!(words(&segmenter, "鬱龘靐齉爩"), ["鬱龘", "靐齉", "爩"]);
+    }
+}
@@ -0,0 +1,345 @@
+//! The english snowball stemmer, also known as Porter2.
+//! <https://snowballstem.org/algorithms/english/stemmer.html>
+
+use super::Word;
+
+/// Words that are not stemmed like the others.
+const EXCEPTIONS: &[(&str, &str)] = &[
+    ("skis", "ski"),
+    ("skies", "sky"),
+    ("dying", "die"),
+    ("lying", "lie"),
+    ("tying", "tie"),
+    ("idly", "idl"),
+    ("gently", "gentl"),
+    ("ugly", "ugli"),
+    ("early", "earli"),
+    ("only", "onli"),
+    ("singly", "singl"),
+    ("sky", "sky"),
+    ("news", "news"),
+    ("howe", "howe"),
+    ("atlas", "atlas"),
+    ("cosmos", "cosmos"),
+    ("bias", "bias"),
+    ("andes", "andes"),
+];
+
+/// Words left untouched once their plural has been removed.
+const INVARIANTS: &[&str] = &[
+    "inning", "outing", "canning", "herring", "earring", "proceed", "exceed", "succeed",
+];
+
+const STEP2: &[(&str, &str)] = &[
+    ("ization", "ize"),
+    ("ational", "ate"),
+    ("fulness", "ful"),
+    ("ousness", "ous"),
+    ("iveness", "ive"),
+    ("tional", "tion"),
+    ("biliti", "ble"),
+    ("lessli", "less"),
+    ("entli", "ent"),
+    ("ation", "ate"),
+    ("alism", "al"),
+    ("aliti", "al"),
+    ("ousli", "ous"),
+    ("iviti", "ive"),
+    ("fulli", "ful"),
+    ("enci", "ence"),
+    ("anci", "ance"),
+    ("abli", "able"),
+    ("izer", "ize"),
+    ("ator", "ate"),
+    ("alli", "al"),
+    ("bli", "ble"),
+    ("ogi", "og"),
+    ("li", ""),
+];
+
+const STEP3: &[(&str, &str)] = &[
+    ("ational", "ate"),
+    ("tional", "tion"),
+    ("alize", "al"),
+    ("icate", "ic"),
+    ("iciti", "ic"),
+    ("ative", ""),
+    ("ical", "ic"),
+    ("ness", ""),
+    ("ful", ""),
+];
+
+const STEP4: &[&str] = &[
+    "ement", "ance", "ence", "able", "ible", "ment", "ant", "ent", "ism", "ate", "iti", "ous",
+    "ive", "ize", "ion", "al", "er", "ic",
+];
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y')
+}
+
+pub fn stem(word: &str) -> String {
+    if word.chars().count() <= 2 {
+        return word.to_string();
+    }
+    if let Some((_, stem)) = EXCEPTIONS.iter().find(|(exception, _)| *exception == word) {
+        return stem.to_string();
+    }
+
+    let mut word = Word::new(word.strip_prefix('\'').unwrap_or(word));
+    // the `y`s acting as consonants are marked as `Y`
+    for i in 0..word.len() {
+        if word.chars[i] == 'y' && (i == 0 || is_vowel(word.chars[i - 1])) {
+            word.chars[i] = 'Y';
+        }
+    }
+
+    let r1 = ["gener", "commun", "arsen"]
+        .iter()
+        .find(|prefix| word.starts_with(prefix))
+        .map_or_else(|| word.region_after(0, is_vowel), |prefix| prefix.len());
+    let r2 = word.region_after(r1, is_vowel);
+
+    step0(&mut word);
+    step1a(&mut word);
+    if INVARIANTS.iter().any(|invariant| word.is(invariant)) {
+        return word.into_string();
+    }
+    step1b(&mut word, r1);
+    step1c(&mut word);
+    step2(&mut word, r1);
+    step3(&mut word, r1, r2);
+    step4(&mut word, r2);
+    step5(&mut word, r1, r2);
+
+    word.into_string().replace('Y', "y")
+}
+
+fn step0(word: &mut Word) {
+    if let Some(suffix) = word.longest_suffix(&["'s'", "'s", "'"]) {
+        word.delete_suffix(suffix);
+    }
+}
+
+fn step1a(word: &mut Word) {
+    match word.longest_suffix(&["sses", "ied", "ies", "us", "ss", "s"]) {
+        Some(suffix @ "sses") => word.replace_suffix(suffix, "ss"),
+        Some(suffix @ ("ied" | "ies")) if word.len() > 4 => word.replace_suffix(suffix, "i"),
+        Some(suffix @ ("ied" | "ies")) => word.replace_suffix(suffix, "ie"),
+        // the `s` is removed only if there is a vowel before the letter preceding it
+        Some("s")
+            if word.len() > 2 && word.chars[..word.len() - 2].iter().any(|c| is_vowel(*c)) =>
+        {
+            word.delete_suffix("s")
+        }
+        _ => (),
+    }
+}
+
+fn step1b(word: &mut Word, r1: usize) {
+    match word.longest_suffix(&["eedly", "eed", "ingly", "edly", "ing", "ed"]) {
+        Some(suffix @ ("eedly" | "eed")) if word.suffix_in(suffix, r1) => {
+            word.replace_suffix(suffix, "ee")
+        }
+        Some("eedly" | "eed") => (),
+        Some(suffix) => {
+            let start = word.suffix_start(suffix);
+            if !word.chars[..start].iter().any(|c| is_vowel(*c)) {
+                return;
+            }
+            word.delete_suffix(suffix);
+
+            if word.ends_with("at") || word.ends_with("bl") || word.ends_with("iz") {
+                word.chars.push('e');
+            } else if ends_with_double(word) {
+                word.chars.pop();
+            } else if ends_with_short_syllable(&word.chars) && r1 >= word.len() {
+                // the word is short
+                word.chars.push('e');
+            }
+        }
+        None => (),
+    }
+}
+
+fn step1c(word: &mut Word) {
+    let len = word.len();
+    if len > 2 && matches!(word.chars[len - 1], 'y' | 'Y') && !is_vowel(word.chars[len - 2]) {
+        word.chars[len - 1] = 'i';
+    }
+}
+
+fn step2(word: &mut Word, r1: usize) {
+    let suffixes: Vec<_> = STEP2.iter().map(|(suffix, _)| *suffix).collect();
+    let suffix = match word.longest_suffix(&suffixes) {
+        Some(suffix) if word.suffix_in(suffix, r1) => suffix,
+        _ => return,
+    };
+    let replacement = STEP2.iter().find(|(s, _)| *s == suffix).unwrap().1;
+
+    match suffix {
+        "ogi" if word.before(suffix) != Some('l') => (),
+        "li" if !matches!(
+            word.before(suffix),
+            Some('c' | 'd' | 'e' | 'g' | 'h' | 'k' | 'm' | 'n' | 'r' | 't')
+        ) => {}
+        _ => word.replace_suffix(suffix, replacement),
+    }
+}
+
+fn step3(word: &mut Word, r1: usize, r2: usize) {
+    let suffixes: Vec<_> = STEP3.iter().map(|(suffix, _)| *suffix).collect();
+    let suffix = match word.longest_suffix(&suffixes) {
+        Some(suffix) if word.suffix_in(suffix, r1) => suffix,
+        _ => return,
+    };
+    let replacement = STEP3.iter().find(|(s, _)| *s == suffix).unwrap().1;
+
+    if suffix != "ative" || word.suffix_in(suffix, r2) {
+        word.replace_suffix(suffix, replacement);
+    }
+}
+
+fn step4(word: &mut Word, r2: usize) {
+    let suffix = match word.longest_suffix(STEP4) {
+        Some(suffix) if word.suffix_in(suffix, r2) => suffix,
+        _ => return,
+    };
+
+    if suffix != "ion" || matches!(word.before(suffix), Some('s' | 't')) {
+        word.delete_suffix(suffix);
+    }
+}
+
+fn step5(word: &mut Word, r1: usize, r2: usize) {
+    if word.ends_with("e") {
+        let short = ends_with_short_syllable(&word.chars[..word.len() - 1]);
+        if word.suffix_in("e", r2) || (word.suffix_in("e", r1) && !short) {
+            word.delete_suffix("e");
+        }
+    } else if word.suffix_in("l", r2) && word.before("l") == Some('l') {
+        word.delete_suffix("l");
+    }
+}
+
+fn ends_with_double(word: &Word) -> bool {
+    ["bb", "dd", "ff", "gg", "mm", "nn", "pp", "rr", "tt"]
+        .iter()
+        .any(|double| word.ends_with(double))
+}
+
+fn ends_with_short_syllable(chars: &[char]) -> bool {
+    match chars {
+        [first, second] => is_vowel(*first) && !is_vowel(*second),
+        [.., first, second, third] => {
+            !is_vowel(*first)
+                && is_vowel(*second)
+                && !is_vowel(*third)
+                && !matches!(third, 'w' | 'x' | 'Y')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stem;
+
+    /// A slice of the vocabulary of the snowball website with the stems it expects.
+    const VOCABULARY: &[(&str, &str)] = &[
+        ("consign", "consign"),
+        ("consigned", "consign"),
+        ("consigning", "consign"),
+        ("consignment", "consign"),
+        ("consist", "consist"),
+        ("consisted", "consist"),
+        ("consistency", "consist"),
+        ("consistent", "consist"),
+        ("consistently", "consist"),
+        ("consisting", "consist"),
+        ("consists", "consist"),
+        ("consolation", "consol"),
+        ("consolations", "consol"),
+        ("consolatory", "consolatori"),
+        ("console", "consol"),
+        ("consoled", "consol"),
+        ("consoles", "consol"),
+        ("consolidate", "consolid"),
+        ("consolidated", "consolid"),
+        ("consolidating", "consolid"),
+        ("consoling", "consol"),
+        ("consolingly", "consol"),
+        ("consols", "consol"),
+        ("consonant", "conson"),
+        ("consort", "consort"),
+        ("consorted", "consort"),
+        ("consorting", "consort"),
+        ("conspicuous", "conspicu"),
+        ("conspicuously", "conspicu"),
+        ("conspiracy", "conspiraci"),
+        ("conspirator", "conspir"),
+        ("conspirators", "conspir"),
+        ("conspire", "conspir"),
+        ("conspired", "conspir"),
+        ("conspiring", "conspir"),
+        ("constable", "constabl"),
+        ("constables", "constabl"),
+        ("constance", "constanc"),
+        ("constancy", "constanc"),
+        ("constant", "constant"),
+        ("knack", "knack"),
+        ("knackeries", "knackeri"),
+        ("knacks", "knack"),
+        ("knag", "knag"),
+        ("knave", "knave"),
+        ("knaves", "knave"),
+        ("knavish", "knavish"),
+        ("kneaded", "knead"),
+        ("kneading", "knead"),
+        ("knee", "knee"),
+        ("kneel", "kneel"),
+        ("kneeled", "kneel"),
+        ("kneeling", "kneel"),
+        ("kneels", "kneel"),
+        ("knees", "knee"),
+        ("knell", "knell"),
+        ("knelt", "knelt"),
+        ("knew", "knew"),
+        ("knick", "knick"),
+        ("knif", "knif"),
+        ("knife", "knife"),
+        ("knight", "knight"),
+        ("knightly", "knight"),
+        ("knights", "knight"),
+        ("knit", "knit"),
+        ("knits", "knit"),
+        ("knitted", "knit"),
+        ("knitting", "knit"),
+        ("knives", "knive"),
+        ("knob", "knob"),
+        ("knobs", "knob"),
+        ("knock", "knock"),
+        ("knocked", "knock"),
+        ("knocker", "knocker"),
+        ("knockers", "knocker"),
+        ("knocking", "knock"),
+        ("knocks", "knock"),
+        ("knopp", "knopp"),
+        ("knot", "knot"),
+        ("knots", "knot"),
+    ];
+
+    #[test]
+    fn snowball_vocabulary() {
+        for (word, expected) in VOCABULARY {
+            assert_eq!(stem(word), *expected, "stem of `{word}`");
+        }
+    }
+
+    #[test]
+    fn short_words_after_the_possessive() {
+        assert_eq!(stem("s's"), "s");
+        assert_eq!(stem("'s"), "'s");
+        assert_eq!(stem("as's"), "as");
+    }
+}
@@ -0,0 +1,439 @@
+//! The french snowball stemmer.
+//! <https://snowballstem.org/algorithms/french/stemmer.html>
+
+use super::Word;
+
+const STEP2A: &[&str] = &[
+    "îmes", "ît", "îtes", "i", "ie", "ies", "ir", "ira", "irai", "iraIent", "irais", "irait",
+    "iras", "irent", "irez", "iriez", "irions", "irons", "iront", "is", "issaIent", "issais",
+    "issait", "issant", "issante", "issantes", "issants", "isse", "issent", "isses", "issez",
+    "issiez", "issions", "issons", "it",
+];
+
+const STEP2B_ION: &[&str] = &["ions"];
+
+const STEP2B_E: &[&str] = &[
+    "é", "ée", "ées", "és", "èrent", "er", "era", "erai", "eraIent", "erais", "erait", "eras",
+    "erez", "eriez", "erions", "erons", "eront", "ez", "iez",
+];
+
+const STEP2B_A: &[&str] = &[
+    "âmes", "ât", "âtes", "a", "ai", "aIent", "ais", "ait", "ant", "ante", "antes", "ants", "as",
+    "asse", "assent", "asses", "assiez", "assions",
+];
+
+fn is_vowel(c: char) -> bool {
+    "aeiouyâàëéêèïîôûù".contains(c)
+}
+
+pub fn stem(word: &str) -> String {
+    let mut word = Word::new(word);
+    mark_consonants(&mut word);
+
+    let rv = rv(&word);
+    let r1 = word.region_after(0, is_vowel);
+    let r2 = word.region_after(r1, is_vowel);
+
+    let altered = match step1(&mut word, rv, r1, r2) {
+        Step1::Altered => true,
+        Step1::Unaltered => step2a(&mut word, rv) || step2b(&mut word, rv, r2),
+    };
+
+    if altered {
+        // step 3
+        match word.chars.last() {
+            Some('Y') => word.replace_suffix("Y", "i"),
+            Some('ç') => word.replace_suffix("ç", "c"),
+            _ => (),
+        }
+    } else {
+        step4(&mut word, rv, r2);
+    }
+
+    undouble(&mut word);
+    unaccent(&mut word);
+
+    word.into_string()
+        .replace('I', "i")
+        .replace('U', "u")
+        .replace('Y', "y")
+}
+
+/// Mark the `u`, `i` and `y` acting as consonants in uppercase.
+fn mark_consonants(word: &mut Word) {
+    for i in 0..word.len() {
+        let previous = i.checked_sub(1).map(|i| word.chars[i]);
+        let next = word.chars.get(i + 1).copied();
+        let after_vowel = previous.is_some_and(is_vowel);
+        let before_vowel = next.is_some_and(is_vowel);
+
+        word.chars[i] = match word.chars[i] {
+            'u' if after_vowel && before_vowel => 'U',
+            'i' if after_vowel && before_vowel => 'I',
+            'y' if after_vowel || before_vowel => 'Y',
+            'u' if previous == Some('q') => 'U',
+            c => c,
+        };
+    }
+}
+
+fn rv(word: &Word) -> usize {
+    if ["par", "col", "tap"]
+        .iter()
+        .any(|prefix| word.starts_with(prefix))
+    {
+        return 3;
+    }
+    match word.chars.as_slice() {
+        [first, second, ..] if is_vowel(*first) && is_vowel(*second) => 3.min(word.len()),
+        _ => (1..word.len())
+            .find(|&i| is_vowel(word.chars[i]))
+            .map_or(word.len(), |i| i + 1),
+    }
+}
+
+enum Step1 {
+    Altered,
+    /// Nothing was removed, or one of the endings that asks for the verb steps was found.
+    Unaltered,
+}
+
+/// Remove the standard suffixes.
+fn step1(word: &mut Word, rv: usize, r1: usize, r2: usize) -> Step1 {
+    let suffixes = [
+        "ance",
+        "iqUe",
+        "isme",
+        "able",
+        "iste",
+        "eux",
+        "ances",
+        "iqUes",
+        "ismes",
+        "ables",
+        "istes",
+        "atrice",
+        "ateur",
+        "ation",
+        "atrices",
+        "ateurs",
+        "ations",
+        "logie",
+        "logies",
+        "usion",
+        "ution",
+        "usions",
+        "utions",
+        "ence",
+        "ences",
+        "ement",
+        "ements",
+        "ité",
+        "ités",
+        "if",
+        "ive",
+        "ifs",
+        "ives",
+        "eaux",
+        "aux",
+        "euse",
+        "euses",
+        "issement",
+        "issements",
+        "amment",
+        "emment",
+        "ment",
+        "ments",
+    ];
+    let suffix = match word.longest_suffix(&suffixes) {
+        Some(suffix) => suffix,
+        None => return Step1::Unaltered,
+    };
+    let in_r1 = word.suffix_in(suffix, r1);
+    let in_r2 = word.suffix_in(suffix, r2);
+    let in_rv = word.suffix_in(suffix, rv);
+
+    match suffix {
+        "ance" | "iqUe" | "isme" | "able" | "iste" | "eux" | "ances" | "iqUes" | "ismes"
+        | "ables" | "istes"
+            if in_r2 =>
+        {
+            word.delete_suffix(suffix);
+        }
+        "atrice" | "ateur" | "ation" | "atrices" | "ateurs" | "ations" if in_r2 => {
+            word.delete_suffix(suffix);
+            if word.ends_with("ic") {
+                if word.suffix_in("ic", r2) {
+                    word.delete_suffix("ic");
+                } else {
+                    word.replace_suffix("ic", "iqU");
+                }
+            }
+        }
+        "logie" | "logies" if in_r2 => word.replace_suffix(suffix, "log"),
+        "usion" | "ution" | "usions" | "utions" if in_r2 => word.replace_suffix(suffix, "u"),
+        "ence" | "ences" if in_r2 => word.replace_suffix(suffix, "ent"),
+        "ement" | "ements" if in_rv => {
+            word.delete_suffix(suffix);
+            if word.suffix_in("iv", r2) {
+                word.delete_suffix("iv");
+                if word.suffix_in("at", r2) {
+                    word.delete_suffix("at");
+                }
+            } else if word.ends_with("eus") {
+                if word.suffix_in("eus", r2) {
+                    word.delete_suffix("eus");
+                } else if word.suffix_in("eus", r1) {
+                    word.replace_suffix("eus", "eux");
+                }
+            } else if word.suffix_in("abl", r2) {
+                word.delete_suffix("abl");
+            } else if word.suffix_in("iqU", r2) {
+                word.delete_suffix("iqU");
+            } else if word.suffix_in("ièr", rv) {
+                word.replace_suffix("ièr", "i");
+            } else if word.suffix_in("Ièr", rv) {
+                word.replace_suffix("Ièr", "i");
+            }
+        }
+        "ité" | "ités" if in_r2 => {
+            word.delete_suffix(suffix);
+            if word.ends_with("abil") {
+                if word.suffix_in("abil", r2) {
+                    word.delete_suffix("abil");
+                } else {
+                    word.replace_suffix("abil", "abl");
+                }
+            } else if word.ends_with("ic") {
+                if word.suffix_in("ic", r2) {
+                    word.delete_suffix("ic");
+                } else {
+                    word.replace_suffix("ic", "iqU");
+                }
+            } else if word.suffix_in("iv", r2) {
+                word.delete_suffix("iv");
+            }
+        }
+        "if" | "ive" | "ifs" | "ives" if in_r2 => {
+            word.delete_suffix(suffix);
+            if word.suffix_in("at", r2) {
+                word.delete_suffix("at");
+                if word.ends_with("ic") {
+                    if word.suffix_in("ic", r2) {
+                        word.delete_suffix("ic");
+                    } else {
+                        word.replace_suffix("ic", "iqU");
+                    }
+                }
+            }
+        }
+        "eaux" => word.replace_suffix(suffix, "eau"),
+        "aux" if in_r1 => word.replace_suffix(suffix, "al"),
+        "euse" | "euses" if in_r2 => word.delete_suffix(suffix),
+        "euse" | "euses" if in_r1 => word.replace_suffix(suffix, "eux"),
+        "issement" | "issements" if in_r1 && word.before(suffix).is_some_and(|c| !is_vowel(c)) => {
+            word.delete_suffix(suffix);
+        }
+        // the following endings are removed but the verb suffixes are still searched for
+        "amment" if in_rv => {
+            word.replace_suffix(suffix, "ant");
+            return Step1::Unaltered;
+        }
+        "emment" if in_rv => {
+            word.replace_suffix(suffix, "ent");
+            return Step1::Unaltered;
+        }
+        "ment" | "ments"
+            if in_rv
+                && word.suffix_start(suffix) > rv
+                && word.before(suffix).is_some_and(is_vowel) =>
+        {
+            word.delete_suffix(suffix);
+            return Step1::Unaltered;
+        }
+        _ => return Step1::Unaltered,
+    }
+
+    Step1::Altered
+}
+
+/// Remove the verb suffixes beginning with an `i`.
+fn step2a(word: &mut Word, rv: usize) -> bool {
+    match word.longest_suffix_in(STEP2A, rv) {
+        Some(suffix)
+            if word.suffix_start(suffix) > rv
+                && word.before(suffix).is_some_and(|c| !is_vowel(c)) =>
+        {
+            word.delete_suffix(suffix);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Remove the other verb suffixes.
+fn step2b(word: &mut Word, rv: usize, r2: usize) -> bool {
+    let suffixes: Vec<_> = [STEP2B_ION, STEP2B_E, STEP2B_A].concat();
+    match word.longest_suffix_in(&suffixes, rv) {
+        Some(suffix) if STEP2B_ION.contains(&suffix) => {
+            let in_r2 = word.suffix_in(suffix, r2);
+            if in_r2 {
+                word.delete_suffix(suffix);
+            }
+            in_r2
+        }
+        Some(suffix) if STEP2B_E.contains(&suffix) => {
+            word.delete_suffix(suffix);
+            true
+        }
+        Some(suffix) => {
+            word.delete_suffix(suffix);
+            if word.suffix_in("e", rv) {
+                word.delete_suffix("e");
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Remove the residual suffixes.
+fn step4(word: &mut Word, rv: usize, r2: usize) {
+    if word.ends_with("s")
+        && !matches!(
+            word.before("s"),
+            Some('a' | 'i' | 'o' | 'u' | 'è' | 's') | None
+        )
+    {
+        word.delete_suffix("s");
+    }
+
+    match word.longest_suffix_in(&["ion", "ier", "ière", "Ier", "Ière", "e", "ë"], rv) {
+        Some(suffix @ "ion")
+            if word.suffix_in(suffix, r2)
+                && word.suffix_start(suffix) > rv
+                && matches!(word.before(suffix), Some('s' | 't')) =>
+        {
+            word.delete_suffix(suffix);
+        }
+        Some(suffix @ ("ier" | "ière" | "Ier" | "Ière")) => word.replace_suffix(suffix, "i"),
+        Some(suffix @ "e") => word.delete_suffix(suffix),
+        Some(suffix @ "ë") if word.ends_with("guë") => word.delete_suffix(suffix),
+        _ => (),
+    }
+}
+
+fn undouble(word: &mut Word) {
+    if ["enn", "onn", "ett", "ell", "eill"]
+        .iter()
+        .any(|suffix| word.ends_with(suffix))
+    {
+        word.chars.pop();
+    }
+}
+
+/// Remove the accent of an `é` or `è` followed by at least one non-vowel at the end of the word.
+fn unaccent(word: &mut Word) {
+    let consonants = word
+        .chars
+        .iter()
+        .rev()
+        .take_while(|c| !is_vowel(**c))
+        .count();
+    if consonants == 0 || consonants == word.len() {
+        return;
+    }
+    let index = word.len() - consonants - 1;
+    if matches!(word.chars[index], 'é' | 'è') {
+        word.chars[index] = 'e';
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stem;
+
+    /// A slice of the vocabulary of the snowball website with the stems it expects.
+    const VOCABULARY: &[(&str, &str)] = &[
+        ("continu", "continu"),
+        ("continua", "continu"),
+        ("continuait", "continu"),
+        ("continuant", "continu"),
+        ("continuation", "continu"),
+        ("continue", "continu"),
+        ("continué", "continu"),
+        ("continuel", "continuel"),
+        ("continuelle", "continuel"),
+        ("continuellement", "continuel"),
+        ("continuelles", "continuel"),
+        ("continuels", "continuel"),
+        ("continuer", "continu"),
+        ("continuez", "continu"),
+        ("continuité", "continu"),
+        ("continuons", "continuon"),
+        ("contorsions", "contors"),
+        ("contour", "contour"),
+        ("contournait", "contourn"),
+        ("contournant", "contourn"),
+        ("contourne", "contourn"),
+        ("contours", "contour"),
+        ("contractait", "contract"),
+        ("contracté", "contract"),
+        ("contractée", "contract"),
+        ("contracter", "contract"),
+        ("contractés", "contract"),
+        ("contractions", "contract"),
+        ("contradictoirement", "contradictoir"),
+        ("contradictoires", "contradictoir"),
+        ("contraindre", "contraindr"),
+        ("contraint", "contraint"),
+        ("contrainte", "contraint"),
+        ("contraintes", "contraint"),
+        ("contraire", "contrair"),
+        ("contraires", "contrair"),
+        ("contraria", "contrari"),
+        ("main", "main"),
+        ("mains", "main"),
+        ("maintenaient", "mainten"),
+        ("maintenait", "mainten"),
+        ("maintenant", "mainten"),
+        ("maintenir", "mainten"),
+        ("maintenue", "maintenu"),
+        ("maintien", "maintien"),
+        ("maintint", "maintint"),
+        ("maire", "mair"),
+        ("maires", "mair"),
+        ("mairie", "mair"),
+        ("mais", "mais"),
+        ("maison", "maison"),
+        ("maisons", "maison"),
+        ("maître", "maîtr"),
+        ("maîtresse", "maîtress"),
+        ("majesté", "majest"),
+        ("majestueuse", "majestu"),
+        ("majestueusement", "majestu"),
+        ("majestueux", "majestu"),
+        ("majeur", "majeur"),
+        ("majorité", "major"),
+        ("malade", "malad"),
+        ("maladie", "malad"),
+        ("maladive", "malad"),
+        ("maladroitement", "maladroit"),
+        ("malédiction", "malédict"),
+        ("malgré", "malgr"),
+        ("malheureuse", "malheur"),
+        ("malheureusement", "malheur"),
+        ("malheureux", "malheur"),
+        ("malignité", "malign"),
+        ("malveillance", "malveil"),
+        ("malveillant", "malveil"),
+        ("maman", "maman"),
+    ];
+
+    #[test]
+    fn snowball_vocabulary() {
+        for (word, expected) in VOCABULARY {
+            assert_eq!(stem(word), *expected, "stem of `{word}`");
+        }
+    }
+}
@@ -0,0 +1,108 @@
+mod english;
+mod french;
+
+use serde::{Deserialize, Serialize};
+
+/// The languages we know how to stem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    English,
+    French,
+}
+
+impl Language {
+    /// Reduce a normalized word to its stem, `running` becomes `run`.
+    pub fn stem(&self, word: &str) -> String {
+        match self {
+            Language::English => english::stem(word),
+            Language::French => french::stem(word),
+        }
+    }
+}
+
+/// A word being stemmed. All the positions are expressed in `char`s.
+struct Word {
+    chars: Vec<char>,
+}
+
+impl Word {
+    fn new(word: &str) -> Self {
+        Word {
+            chars: word.chars().collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    fn is(&self, word: &str) -> bool {
+        self.chars.iter().copied().eq(word.chars())
+    }
+
+    fn starts_with(&self, prefix: &str) -> bool {
+        self.chars
+            .iter()
+            .copied()
+            .take(prefix.chars().count())
+            .eq(prefix.chars())
+    }
+
+    fn ends_with(&self, suffix: &str) -> bool {
+        let suffix: Vec<char> = suffix.chars().collect();
+        self.chars.ends_with(&suffix)
+    }
+
+    /// Position of the suffix, supposing the word ends with it.
+    fn suffix_start(&self, suffix: &str) -> usize {
+        self.len() - suffix.chars().count()
+    }
+
+    /// Returns `true` if the word ends with the suffix and the suffix is entirely in the region.
+    fn suffix_in(&self, suffix: &str, region: usize) -> bool {
+        self.ends_with(suffix) && self.suffix_start(suffix) >= region
+    }
+
+    /// The longest of the suffixes the word ends with.
+    fn longest_suffix<'a>(&self, suffixes: &[&'a str]) -> Option<&'a str> {
+        self.longest_suffix_in(suffixes, 0)
+    }
+
+    /// The longest of the suffixes the word ends with, that is entirely in the region.
+    fn longest_suffix_in<'a>(&self, suffixes: &[&'a str], region: usize) -> Option<&'a str> {
+        suffixes
+            .iter()
+            .filter(|suffix| self.suffix_in(suffix, region))
+            .max_by_key(|suffix| suffix.chars().count())
+            .copied()
+    }
+
+    /// The `char` right before the suffix.
+    fn before(&self, suffix: &str) -> Option<char> {
+        self.suffix_start(suffix)
+            .checked_sub(1)
+            .map(|index| self.chars[index])
+    }
+
+    fn replace_suffix(&mut self, suffix: &str, with: &str) {
+        let start = self.suffix_start(suffix);
+        self.chars.truncate(start);
+        self.chars.extend(with.chars());
+    }
+
+    fn delete_suffix(&mut self, suffix: &str) {
+        self.replace_suffix(suffix, "");
+    }
+
+    /// The region starting after the first non-vowel following a vowel, looking from `from`.
+    fn region_after(&self, from: usize, is_vowel: fn(char) -> bool) -> usize {
+        (from + 1..self.len())
+            .find(|&i| is_vowel(self.chars[i - 1]) && !is_vowel(self.chars[i]))
+            .map_or(self.len(), |i| i + 1)
+    }
+
+    fn into_string(self) -> String {
+        self.chars.into_iter().collect()
+    }
+}
@@ -0,0 +1,65 @@
+//! The documents can carry an embedding computed outside of minisearch in their `_vectors`
+//! attribute, like `[0.12, -0.4, 0.8]`. The queries with a `vector` are compared to all of them.
+
+use roaring::RoaringBitmap;
+use serde_json::Value;
+
+use crate::{DocId, Index};
+
+/// The attribute containing the embedding of the documents.
+pub const VECTORS_FIELD: &str = "_vectors";
+
+/// Read the embedding of a document, it must have exactly `dimensions` numbers.
+pub fn parse_vector(value: &Value, dimensions: usize) -> Result<Vec<f32>, String> {
+    let invalid = || format!("`{VECTORS_FIELD}` must be an array of {dimensions} numbers.");
+    let vector = value
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|value| value.as_f64().map(|value| value as f32))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(invalid)?;
+    if vector.len() == dimensions {
+        Ok(vector)
+    } else {
+        Err(format!(
+            "`{VECTORS_FIELD}` has {} dimensions instead of {dimensions}.",
+            vector.len()
+        ))
+    }
+}
+
+/// The cosine similarity of two vectors, between -1 and 1.
+fn cosine(left: &[f32], right: &[f32]) -> f64 {
+    let (mut dot, mut left_norm, mut right_norm) = (0.0_f64, 0.0_f64, 0.0_f64);
+    for (left, right) in left.iter().zip(right) {
+        let (left, right) = (*left as f64, *right as f64);
+        dot += left * right;
+        left_norm += left * left;
+        right_norm += right * right;
+    }
+    if left_norm == 0.0 || right_norm == 0.0 {
+        0.0
+    } else {
+        dot / (left_norm.sqrt() * right_norm.sqrt())
+    }
+}
+
+/// The documents having a vector along with their similarity to a vector between 0 and 1,
+/// the most similar first. Every vector of the index is compared to the query.
+pub fn similarities<I: Index>(
+    index: &I,
+    vector: &[f32],
+    filter: Option<&RoaringBitmap>,
+) -> Vec<(DocId, f64)> {
+    let mut similarities: Vec<_> = index
+        .vectors()
+        .into_iter()
+        .filter(|(docid, _)| filter.is_none_or(|filter| filter.contains(*docid)))
+        .map(|(docid, other)| (docid, (1.0 + cosine(vector, &other)) / 2.0))
+        .collect();
+    similarities.sort_unstable_by(|(left_id, left), (right_id, right)| {
+        right.total_cmp(left).then(left_id.cmp(right_id))
+    });
+    similarities
+}